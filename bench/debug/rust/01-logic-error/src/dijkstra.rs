@@ -0,0 +1,573 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::graph::{Graph, Weight};
+use crate::priority_queue::MinPriorityQueue;
+
+/// Errors reported by shortest-path searches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShortestPathError {
+    /// The graph has an edge with a negative weight, which Dijkstra's
+    /// algorithm cannot handle; use [`bellman_ford`] instead.
+    NegativeEdge,
+    /// A cycle of negative total weight is reachable from the source, so
+    /// shortest distances are unbounded.
+    NegativeCycle,
+}
+
+impl fmt::Display for ShortestPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortestPathError::NegativeEdge => write!(f, "graph has a negative edge weight"),
+            ShortestPathError::NegativeCycle => {
+                write!(f, "a negative cycle is reachable from the source")
+            }
+        }
+    }
+}
+
+/// Run Dijkstra's shortest-path algorithm from `source` to `target`.
+///
+/// Returns `(cost, path)` where `path` is a vector of node indices
+/// from source to target (inclusive).  Returns `Ok(None)` if unreachable,
+/// and `Err(ShortestPathError::NegativeEdge)` if any edge weight is
+/// negative, since Dijkstra would then return a wrong path.
+pub fn dijkstra<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+    target: usize,
+) -> Result<Option<(W, Vec<usize>)>, ShortestPathError> {
+    dijkstra_with_blocked(graph, source, target, &HashSet::new(), &HashSet::new())
+}
+
+/// Dijkstra's algorithm with some of the graph masked out.
+///
+/// Edges `(from, to)` in `blocked_edges` (every parallel copy) and edges
+/// into nodes in `blocked_nodes` are ignored, as if they had been
+/// removed.  Used by the spur searches of [`k_shortest_paths`].  Like
+/// [`dijkstra`], returns `Err(ShortestPathError::NegativeEdge)` if any
+/// edge weight is negative.
+pub fn dijkstra_with_blocked<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+    target: usize,
+    blocked_edges: &HashSet<(usize, usize)>,
+    blocked_nodes: &HashSet<usize>,
+) -> Result<Option<(W, Vec<usize>)>, ShortestPathError> {
+    check_non_negative(graph)?;
+    Ok(search_with_blocked(graph, source, target, blocked_edges, blocked_nodes))
+}
+
+/// Return `Err(ShortestPathError::NegativeEdge)` if `graph` has a
+/// negative edge.  Every Dijkstra-family search calls this first: with a
+/// negative edge they would return wrong answers, and with a negative
+/// cycle the searches that reopen nodes would never finish.
+fn check_non_negative<W: Weight>(graph: &Graph<W>) -> Result<(), ShortestPathError> {
+    if graph.has_negative_edge() {
+        Err(ShortestPathError::NegativeEdge)
+    } else {
+        Ok(())
+    }
+}
+
+/// [`dijkstra_with_blocked`] without the negative-edge check, for callers
+/// that already made it.
+fn search_with_blocked<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+    target: usize,
+    blocked_edges: &HashSet<(usize, usize)>,
+    blocked_nodes: &HashSet<usize>,
+) -> Option<(W, Vec<usize>)> {
+    let n = graph.num_nodes();
+    let mut dist = vec![W::MAX; n];
+    let mut prev: Vec<Option<usize>> = vec![None; n];
+    let mut visited = vec![false; n];
+
+    let mut pq: MinPriorityQueue<W> = MinPriorityQueue::new(n);
+
+    dist[source] = W::ZERO;
+    pq.insert(source, W::ZERO);
+
+    while let Some(entry) = pq.extract_min() {
+        let u = entry.item;
+        let cost_u = entry.priority;
+
+        // Early termination: target reached.
+        if u == target {
+            return Some((cost_u, reconstruct_path(&prev, source, target)));
+        }
+
+        if visited[u] {
+            continue;
+        }
+        visited[u] = true;
+
+        // Skip stale entries whose distance was already improved.
+        if cost_u > dist[u] {
+            continue;
+        }
+
+        for edge in graph.neighbors(u) {
+            let v = edge.to;
+            if blocked_nodes.contains(&v) || blocked_edges.contains(&(u, v)) {
+                continue;
+            }
+            let new_dist = cost_u.saturating_add(edge.weight);
+
+            if new_dist < dist[v] {
+                dist[v] = new_dist;
+                prev[v] = Some(u);
+
+                if pq.contains(v) {
+                    pq.decrease_key(v, new_dist);
+                } else {
+                    pq.insert(v, new_dist);
+                }
+            }
+        }
+    }
+
+    // Target was never extracted — unreachable.
+    if dist[target] == W::MAX {
+        None
+    } else {
+        Some((dist[target], reconstruct_path(&prev, source, target)))
+    }
+}
+
+/// Shortest distances from `source` to every node.
+///
+/// Unreachable nodes get `W::MAX` (`u32::MAX` for a default `Graph`).
+/// Runs a single Dijkstra to exhaustion rather than one search per
+/// target.  Returns `Err(ShortestPathError::NegativeEdge)` if any edge
+/// weight is negative.
+#[allow(dead_code)]
+pub fn shortest_distances<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+) -> Result<Vec<W>, ShortestPathError> {
+    shortest_path_tree(graph, source).map(|(dist, _)| dist)
+}
+
+/// Shortest distances from `source` plus the `prev` array of the
+/// shortest-path tree, so any path can be rebuilt with
+/// [`reconstruct_path`].  `prev[source]` and `prev` of unreachable nodes
+/// are `None`.  Returns `Err(ShortestPathError::NegativeEdge)` if any
+/// edge weight is negative.
+pub fn shortest_path_tree<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+) -> Result<(Vec<W>, Vec<Option<usize>>), ShortestPathError> {
+    check_non_negative(graph)?;
+    let n = graph.num_nodes();
+    let mut dist = vec![W::MAX; n];
+    let mut prev: Vec<Option<usize>> = vec![None; n];
+
+    let mut pq: MinPriorityQueue<W> = MinPriorityQueue::new(n);
+
+    dist[source] = W::ZERO;
+    pq.insert(source, W::ZERO);
+
+    while let Some(entry) = pq.extract_min() {
+        let u = entry.item;
+        let cost_u = entry.priority;
+
+        for edge in graph.neighbors(u) {
+            let v = edge.to;
+            let new_dist = cost_u.saturating_add(edge.weight);
+
+            if new_dist < dist[v] {
+                dist[v] = new_dist;
+                prev[v] = Some(u);
+
+                if pq.contains(v) {
+                    pq.decrease_key(v, new_dist);
+                } else {
+                    pq.insert(v, new_dist);
+                }
+            }
+        }
+    }
+
+    Ok((dist, prev))
+}
+
+/// Bellman-Ford shortest distances from `source` to every node.
+///
+/// Unlike Dijkstra this is correct with negative edge weights.
+/// Unreachable nodes get `W::MAX`.  Returns
+/// `Err(ShortestPathError::NegativeCycle)` if a negative cycle is
+/// reachable from `source`.  O(V * E).
+#[allow(dead_code)]
+pub fn bellman_ford<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+) -> Result<Vec<W>, ShortestPathError> {
+    let mut dist = vec![W::MAX; graph.num_nodes()];
+    dist[source] = W::ZERO;
+
+    for _ in 1..graph.num_nodes() {
+        let mut changed = false;
+        for (u, v, w) in graph.edges() {
+            if dist[u] == W::MAX {
+                continue;
+            }
+            let candidate = dist[u].saturating_add(w);
+            if candidate < dist[v] {
+                dist[v] = candidate;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(dist);
+        }
+    }
+
+    // Any edge that still relaxes after V - 1 rounds lies on or behind a
+    // negative cycle.
+    let relaxes = graph
+        .edges()
+        .any(|(u, v, w)| dist[u] != W::MAX && dist[u].saturating_add(w) < dist[v]);
+    if relaxes {
+        Err(ShortestPathError::NegativeCycle)
+    } else {
+        Ok(dist)
+    }
+}
+
+/// Walk the predecessor chain from target back to source.
+pub fn reconstruct_path(prev: &[Option<usize>], source: usize, target: usize) -> Vec<usize> {
+    let mut path = Vec::new();
+    let mut current = target;
+
+    loop {
+        path.push(current);
+        if current == source {
+            break;
+        }
+        match prev[current] {
+            Some(p) => current = p,
+            None => break, // no path
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+/// Paths from `source` to each of `targets` through a `prev` array such
+/// as the one from [`shortest_path_tree`].
+///
+/// Each path is walked with [`reconstruct_path`]; a target whose chain
+/// stops before reaching `source` is unreachable and yields `None`.
+#[allow(dead_code)]
+pub fn reconstruct_paths(
+    prev: &[Option<usize>],
+    source: usize,
+    targets: &[usize],
+) -> Vec<Option<Vec<usize>>> {
+    targets
+        .iter()
+        .map(|&target| {
+            let path = reconstruct_path(prev, source, target);
+            (path[0] == source).then_some(path)
+        })
+        .collect()
+}
+
+/// A* search from `source` to `target`.
+///
+/// Queue priorities are `g + h`, where `g` is the true cost from
+/// `source` (tracked separately) and `h = heuristic(node)` estimates the
+/// remaining cost.  The result is optimal when the heuristic is
+/// admissible (never overestimates).  Nodes are reopened when a cheaper
+/// `g` is found, so an admissible but inconsistent heuristic is fine.
+/// With a zero heuristic this is Dijkstra's algorithm.  Returns
+/// `Err(ShortestPathError::NegativeEdge)` if any edge weight is negative.
+#[allow(dead_code)]
+pub fn astar<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+    target: usize,
+    heuristic: impl Fn(usize) -> W,
+) -> Result<Option<(W, Vec<usize>)>, ShortestPathError> {
+    check_non_negative(graph)?;
+    let n = graph.num_nodes();
+    let mut g_cost = vec![W::MAX; n];
+    let mut prev: Vec<Option<usize>> = vec![None; n];
+
+    let mut pq: MinPriorityQueue<W> = MinPriorityQueue::new(n);
+
+    g_cost[source] = W::ZERO;
+    pq.insert(source, heuristic(source));
+
+    while let Some(entry) = pq.extract_min() {
+        let u = entry.item;
+
+        if u == target {
+            return Ok(Some((g_cost[u], reconstruct_path(&prev, source, target))));
+        }
+
+        for edge in graph.neighbors(u) {
+            let v = edge.to;
+            let tentative = g_cost[u].saturating_add(edge.weight);
+
+            if tentative < g_cost[v] {
+                g_cost[v] = tentative;
+                prev[v] = Some(u);
+
+                let estimate = tentative.saturating_add(heuristic(v));
+                if pq.contains(v) {
+                    pq.decrease_key(v, estimate);
+                } else {
+                    pq.insert(v, estimate);
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// The `k` shortest loopless paths from `source` to `target` (Yen's
+/// algorithm), in increasing cost order.
+///
+/// Each new path is found by taking every prefix ("root") of the last
+/// accepted path, blocking the edges that earlier paths with the same
+/// root take next and the root's own nodes, and searching for the
+/// cheapest "spur" path from the root's last node to `target`.  Returns
+/// fewer than `k` paths if the graph has fewer loopless routes, and
+/// `Err(ShortestPathError::NegativeEdge)` if any edge weight is negative.
+#[allow(dead_code)]
+pub fn k_shortest_paths<W: Weight>(
+    graph: &Graph<W>,
+    source: usize,
+    target: usize,
+    k: usize,
+) -> Result<Vec<(W, Vec<usize>)>, ShortestPathError> {
+    check_non_negative(graph)?;
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+    let no_blocks = HashSet::new();
+    let Some(first) = search_with_blocked(graph, source, target, &no_blocks, &HashSet::new())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut accepted = vec![first];
+    let mut candidates: Vec<(W, Vec<usize>)> = Vec::new();
+
+    while accepted.len() < k {
+        let last = accepted[accepted.len() - 1].1.clone();
+
+        for i in 0..last.len() - 1 {
+            let spur_node = last[i];
+            let root = &last[..=i];
+
+            let blocked_edges: HashSet<(usize, usize)> = accepted
+                .iter()
+                .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                .map(|(_, p)| (p[i], p[i + 1]))
+                .collect();
+            let blocked_nodes: HashSet<usize> = root[..i].iter().copied().collect();
+
+            let Some((spur_cost, spur_path)) =
+                search_with_blocked(graph, spur_node, target, &blocked_edges, &blocked_nodes)
+            else {
+                continue;
+            };
+
+            let mut path = root[..i].to_vec();
+            path.extend(spur_path);
+            let cost = path_cost(graph, root).saturating_add(spur_cost);
+
+            let known = accepted.iter().chain(candidates.iter()).any(|(_, p)| *p == path);
+            if !known {
+                candidates.push((cost, path));
+            }
+        }
+
+        // Cheapest candidate; ties go to the one found first.
+        let Some(best) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (cost, _))| *cost)
+            .map(|(idx, _)| idx)
+        else {
+            break;
+        };
+        accepted.push(candidates.remove(best));
+    }
+
+    Ok(accepted)
+}
+
+/// Total weight of `path`, taking the cheapest edge between each pair of
+/// consecutive nodes.
+fn path_cost<W: Weight>(graph: &Graph<W>, path: &[usize]) -> W {
+    path.windows(2)
+        .map(|pair| {
+            graph
+                .neighbors(pair[0])
+                .iter()
+                .filter(|e| e.to == pair[1])
+                .map(|e| e.weight)
+                .min()
+                .unwrap_or(W::MAX)
+        })
+        .fold(W::ZERO, W::saturating_add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::build_benchmark_graph;
+
+    #[test]
+    fn astar_finds_optimal_path_with_admissible_heuristic() {
+        let graph = build_benchmark_graph();
+        // Lower bounds on the remaining cost to E from A, B, C, D, E.
+        let h = [6, 4, 0, 4, 0];
+
+        let result = astar(&graph, 0, 4, |u| h[u]);
+        assert_eq!(result, Ok(Some((7, vec![0, 1, 3, 4]))));
+    }
+
+    #[test]
+    fn astar_with_zero_heuristic_matches_dijkstra() {
+        let graph = build_benchmark_graph();
+        for target in 0..graph.num_nodes() {
+            assert_eq!(astar(&graph, 0, target, |_| 0), dijkstra(&graph, 0, target));
+        }
+    }
+
+    #[test]
+    fn k_shortest_paths_returns_routes_in_cost_order() {
+        // 0 -> 3 has four loopless routes: 0-1-2-3 (3), 0-2-3 (3),
+        // 0-1-3 (4) and 0-3 (5).
+        let mut graph = Graph::new(vec!["S", "X", "Y", "T"]);
+        graph.add_edge(0, 1, 1);
+        graph.add_edge(0, 2, 2);
+        graph.add_edge(1, 2, 1);
+        graph.add_edge(1, 3, 3);
+        graph.add_edge(2, 3, 1);
+        graph.add_edge(0, 3, 5);
+
+        let paths = k_shortest_paths(&graph, 0, 3, 10).unwrap();
+        let costs: Vec<u32> = paths.iter().map(|(c, _)| *c).collect();
+        assert_eq!(costs, vec![3, 3, 4, 5]);
+
+        let routes: Vec<&[usize]> = paths.iter().map(|(_, p)| p.as_slice()).collect();
+        assert!(routes[..2].contains(&&[0, 1, 2, 3][..]));
+        assert!(routes[..2].contains(&&[0, 2, 3][..]));
+        assert_eq!(routes[2], &[0, 1, 3]);
+        assert_eq!(routes[3], &[0, 3]);
+    }
+
+    #[test]
+    fn k_shortest_paths_stops_when_routes_run_out() {
+        let graph = build_benchmark_graph();
+
+        let paths = k_shortest_paths(&graph, 0, 4, 5).unwrap();
+        assert_eq!(paths, vec![(7, vec![0, 1, 3, 4]), (10, vec![0, 2, 4])]);
+
+        assert_eq!(
+            k_shortest_paths(&graph, 0, 4, 1),
+            Ok(vec![dijkstra(&graph, 0, 4).unwrap().unwrap()])
+        );
+        assert_eq!(k_shortest_paths(&graph, 0, 4, 0), Ok(vec![]));
+        assert_eq!(k_shortest_paths(&graph, 4, 0, 3), Ok(vec![]));
+    }
+
+    #[test]
+    fn shortest_distances_match_per_target_dijkstra() {
+        let graph = build_benchmark_graph();
+
+        for source in 0..graph.num_nodes() {
+            let dist = shortest_distances(&graph, source).unwrap();
+            for (target, &d) in dist.iter().enumerate() {
+                match dijkstra(&graph, source, target).unwrap() {
+                    Some((cost, _)) => assert_eq!(d, cost),
+                    None => assert_eq!(d, u32::MAX),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shortest_path_tree_reconstructs_paths() {
+        let graph = build_benchmark_graph();
+        let (dist, prev) = shortest_path_tree(&graph, 0).unwrap();
+
+        assert_eq!(dist, vec![0, 2, 10, 3, 7]);
+        assert_eq!(prev[0], None);
+        assert_eq!(reconstruct_path(&prev, 0, 4), vec![0, 1, 3, 4]);
+        assert_eq!(reconstruct_path(&prev, 0, 2), vec![0, 2]);
+    }
+
+    fn graph_from(num_nodes: usize, edges: &[(usize, usize, i64)]) -> Graph<i64> {
+        let labels: Vec<String> = (0..num_nodes).map(|i| i.to_string()).collect();
+        let mut graph = Graph::with_labels(labels.iter().map(String::as_str).collect());
+        for &(from, to, weight) in edges {
+            graph.add_edge(from, to, weight);
+        }
+        graph
+    }
+
+    #[test]
+    fn negative_edge_rejects_dijkstra_but_not_bellman_ford() {
+        // 0 -> 1 -> 2 beats 0 -> 2 only because of the negative edge.
+        let graph = graph_from(5, &[(0, 1, 4), (1, 2, -3), (0, 2, 2), (2, 3, 1)]);
+        assert!(graph.has_negative_edge());
+        assert_eq!(dijkstra(&graph, 0, 2), Err(ShortestPathError::NegativeEdge));
+
+        let dist = bellman_ford(&graph, 0).unwrap();
+        assert_eq!(dist, vec![0, 4, 1, 2, i64::MAX]);
+    }
+
+    #[test]
+    fn negative_cycle_is_rejected_by_every_dijkstra_search() {
+        // 1 -> 2 -> 1 costs -2 per lap; searches that reopen nodes used to
+        // go round it forever.
+        let graph = graph_from(3, &[(0, 1, 1), (1, 2, -3), (2, 1, 1)]);
+        let err = ShortestPathError::NegativeEdge;
+
+        assert_eq!(dijkstra(&graph, 0, 2).unwrap_err(), err);
+        let no_blocks = HashSet::new();
+        let blocked = dijkstra_with_blocked(&graph, 0, 2, &no_blocks, &HashSet::new());
+        assert_eq!(blocked.unwrap_err(), err);
+        assert_eq!(shortest_distances(&graph, 0).unwrap_err(), err);
+        assert_eq!(shortest_path_tree(&graph, 0).unwrap_err(), err);
+        assert_eq!(astar(&graph, 0, 2, |_| 0).unwrap_err(), err);
+        assert_eq!(k_shortest_paths(&graph, 0, 2, 3).unwrap_err(), err);
+        assert_eq!(bellman_ford(&graph, 0), Err(ShortestPathError::NegativeCycle));
+    }
+
+    #[test]
+    fn bellman_ford_reports_negative_cycle() {
+        let graph = graph_from(4, &[(0, 1, 1), (1, 2, -2), (2, 1, 1), (2, 3, 1)]);
+        assert_eq!(bellman_ford(&graph, 0), Err(ShortestPathError::NegativeCycle));
+
+        // The same cycle is fine when it cannot be reached from the source.
+        assert!(bellman_ford(&graph, 3).is_ok());
+    }
+
+    #[test]
+    fn bellman_ford_matches_dijkstra_on_benchmark_graph() {
+        let graph = build_benchmark_graph();
+        assert!(!graph.has_negative_edge());
+        assert_eq!(bellman_ford(&graph, 0), shortest_distances(&graph, 0));
+    }
+
+    #[test]
+    fn reconstruct_paths_marks_unreachable_targets() {
+        // From B, E is reachable but A is not.
+        let graph = build_benchmark_graph();
+        let (_, prev) = shortest_path_tree(&graph, 1).unwrap();
+
+        let paths = reconstruct_paths(&prev, 1, &[4, 0, 1]);
+        assert_eq!(paths, vec![Some(vec![1, 3, 4]), None, Some(vec![1])]);
+    }
+}
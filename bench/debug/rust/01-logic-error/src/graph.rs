@@ -1,39 +1,321 @@
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// An edge weight type: `u32` by default, or a signed type such as `i64`
+/// for graphs that need negative edges (see `dijkstra::bellman_ford`).
+pub trait Weight: Copy + Ord + fmt::Display + FromStr {
+    const ZERO: Self;
+    /// Distance of a node that cannot be reached.
+    const MAX: Self;
+
+    fn saturating_add(self, other: Self) -> Self;
+}
+
+macro_rules! impl_weight {
+    ($($t:ty),*) => {$(
+        impl Weight for $t {
+            const ZERO: Self = 0;
+            const MAX: Self = <$t>::MAX;
+
+            fn saturating_add(self, other: Self) -> Self {
+                <$t>::saturating_add(self, other)
+            }
+        }
+    )*};
+}
+
+impl_weight!(u32, u64, i64);
+
 /// A directed, weighted graph stored as an adjacency list.
 ///
-/// Nodes are identified by `usize` indices. Each edge has a `u32` weight.
+/// Nodes are identified by `usize` indices. Each edge has a weight of type
+/// `W`, `u32` unless chosen otherwise.
 
 #[derive(Debug, Clone)]
-pub struct Edge {
+pub struct Edge<W = u32> {
     pub to: usize,
-    pub weight: u32,
+    pub weight: W,
 }
 
-pub struct Graph {
-    adj: Vec<Vec<Edge>>,
+pub struct Graph<W = u32> {
+    adj: Vec<Vec<Edge<W>>>,
     labels: Vec<String>,
+    /// Incoming edges per node, built on the first `predecessors` call
+    /// and cleared whenever an edge changes.
+    rev: OnceCell<Vec<Vec<Edge<W>>>>,
 }
 
 impl Graph {
     pub fn new(labels: Vec<&str>) -> Self {
+        Graph::with_labels(labels)
+    }
+
+    /// Parse a graph from lines of `from to weight`, e.g. `A B 2`.
+    ///
+    /// Nodes are numbered in order of first appearance.  Blank lines and
+    /// everything after a `#` are ignored.  A malformed line yields an
+    /// `Err` naming its line number and contents.
+    #[allow(dead_code)]
+    pub fn from_edge_list(text: &str) -> Result<Graph, String> {
+        let mut graph = Graph::new(Vec::new());
+        let mut index: HashMap<&str, usize> = HashMap::new();
+
+        for (lineno, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [from, to, weight] = fields[..] else {
+                return Err(format!(
+                    "line {}: expected `from to weight`, got {:?}",
+                    lineno + 1,
+                    raw
+                ));
+            };
+            let weight: u32 = weight.parse().map_err(|e| {
+                format!("line {}: invalid weight {:?} in {:?}: {}", lineno + 1, weight, raw, e)
+            })?;
+
+            let [from, to] = [from, to].map(|label| {
+                *index.entry(label).or_insert_with(|| {
+                    graph.labels.push(label.to_string());
+                    graph.adj.push(Vec::new());
+                    graph.labels.len() - 1
+                })
+            });
+            graph.add_edge(from, to, weight);
+        }
+
+        Ok(graph)
+    }
+}
+
+impl<W: Weight> Graph<W> {
+    /// Like [`Graph::new`], for any weight type, e.g.
+    /// `Graph::<i64>::with_labels(labels)`.
+    pub fn with_labels(labels: Vec<&str>) -> Self {
         let n = labels.len();
         Graph {
             adj: vec![Vec::new(); n],
             labels: labels.into_iter().map(String::from).collect(),
+            rev: OnceCell::new(),
         }
     }
 
-    pub fn add_edge(&mut self, from: usize, to: usize, weight: u32) {
+    /// Add a node with no edges and return its index.
+    ///
+    /// Labels are unique: if `label` already exists, nothing is added and
+    /// the existing node's index is returned.
+    #[allow(dead_code)]
+    pub fn add_node(&mut self, label: &str) -> usize {
+        if let Some(existing) = self.node_index(label) {
+            return existing;
+        }
+        self.labels.push(label.to_string());
+        self.adj.push(Vec::new());
+        self.rev.take();
+        self.adj.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize, weight: W) {
         self.adj[from].push(Edge { to, weight });
+        self.rev.take();
+    }
+
+    /// Add edges `a -> b` and `b -> a`, both with `weight`.
+    #[allow(dead_code)]
+    pub fn add_undirected_edge(&mut self, a: usize, b: usize, weight: W) {
+        self.add_edge(a, b, weight);
+        self.add_edge(b, a, weight);
+    }
+
+    /// Remove the first `from -> to` edge, returning whether one existed.
+    ///
+    /// With parallel edges only the earliest-added copy is removed; call
+    /// again to remove the next one.
+    #[allow(dead_code)]
+    pub fn remove_edge(&mut self, from: usize, to: usize) -> bool {
+        match self.adj[from].iter().position(|e| e.to == to) {
+            Some(i) => {
+                self.adj[from].remove(i);
+                self.rev.take();
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn neighbors(&self, u: usize) -> &[Edge] {
+    /// Set the weight of the first `from -> to` edge in place, returning
+    /// whether one existed.  Parallel copies after the first are left
+    /// untouched.
+    #[allow(dead_code)]
+    pub fn set_edge_weight(&mut self, from: usize, to: usize, weight: W) -> bool {
+        match self.adj[from].iter_mut().find(|e| e.to == to) {
+            Some(edge) => {
+                edge.weight = weight;
+                self.rev.take();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep only the cheapest edge for each `(from, to)` pair, dropping
+    /// the other parallel copies.  Optional: Dijkstra is correct either
+    /// way, this just saves redundant relaxations.  Mutates the graph;
+    /// surviving edges keep the position of the first copy.
+    #[allow(dead_code)]
+    pub fn collapse_parallel_edges(&mut self) {
+        for edges in &mut self.adj {
+            let mut kept: Vec<Edge<W>> = Vec::with_capacity(edges.len());
+            for edge in edges.drain(..) {
+                match kept.iter_mut().find(|k| k.to == edge.to) {
+                    Some(k) => k.weight = k.weight.min(edge.weight),
+                    None => kept.push(edge),
+                }
+            }
+            *edges = kept;
+        }
+        self.rev.take();
+    }
+
+    pub fn neighbors(&self, u: usize) -> &[Edge<W>] {
         &self.adj[u]
     }
 
+    /// Whether there is at least one `from -> to` edge.
+    #[allow(dead_code)]
+    pub fn contains_edge(&self, from: usize, to: usize) -> bool {
+        self.edge_weight(from, to).is_some()
+    }
+
+    /// Weight of the first `from -> to` edge, the same one
+    /// `set_edge_weight` and `remove_edge` act on.
+    #[allow(dead_code)]
+    pub fn edge_weight(&self, from: usize, to: usize) -> Option<W> {
+        self.adj[from].iter().find(|e| e.to == to).map(|e| e.weight)
+    }
+
+    /// Incoming edges of `u`.  Each returned `Edge` has `to` set to the
+    /// *source* node, so `(e.to, e.weight)` reads as "from `e.to`, cost
+    /// `e.weight`".
+    ///
+    /// The first call after any edge change rebuilds the whole reverse
+    /// adjacency in O(V + E); later calls only clone `u`'s list.  Avoid
+    /// interleaving edits and `predecessors` calls in hot loops.
+    #[allow(dead_code)]
+    pub fn predecessors(&self, u: usize) -> Vec<Edge<W>> {
+        self.reverse_adjacency()[u].clone()
+    }
+
+    /// Number of outgoing edges of `u`, counting parallel edges.
+    #[allow(dead_code)]
+    pub fn out_degree(&self, u: usize) -> usize {
+        self.adj[u].len()
+    }
+
+    /// Nodes with no outgoing and no incoming edges, in index order.
+    /// Builds the reverse adjacency if it is not cached (see
+    /// [`Graph::predecessors`]).
+    #[allow(dead_code)]
+    pub fn isolated_nodes(&self) -> Vec<usize> {
+        let rev = self.reverse_adjacency();
+        (0..self.adj.len())
+            .filter(|&u| self.adj[u].is_empty() && rev[u].is_empty())
+            .collect()
+    }
+
+    /// Labels of [`Graph::isolated_nodes`].
+    #[allow(dead_code)]
+    pub fn isolated_labels(&self) -> Vec<&str> {
+        self.isolated_nodes().into_iter().map(|u| self.label(u)).collect()
+    }
+
+    /// The cached reverse adjacency, built on demand.
+    fn reverse_adjacency(&self) -> &[Vec<Edge<W>>] {
+        self.rev.get_or_init(|| {
+            let mut rev = vec![Vec::new(); self.adj.len()];
+            for (from, edges) in self.adj.iter().enumerate() {
+                for edge in edges {
+                    rev[edge.to].push(Edge { to: from, weight: edge.weight });
+                }
+            }
+            rev
+        })
+    }
+
     pub fn num_nodes(&self) -> usize {
         self.adj.len()
     }
 
+    /// Every edge as `(from, to, weight)`, grouped by `from` in node
+    /// order and otherwise in insertion order.  Borrows the graph and
+    /// allocates nothing.
+    #[allow(dead_code)]
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, W)> + '_ {
+        self.adj
+            .iter()
+            .enumerate()
+            .flat_map(|(from, edges)| edges.iter().map(move |e| (from, e.to, e.weight)))
+    }
+
+    /// Whether any edge has a negative weight, which rules out Dijkstra.
+    pub fn has_negative_edge(&self) -> bool {
+        self.adj.iter().flatten().any(|e| e.weight < W::ZERO)
+    }
+
+    /// Total number of directed edges; an undirected edge counts twice.
+    #[allow(dead_code)]
+    pub fn num_edges(&self) -> usize {
+        self.adj.iter().map(Vec::len).sum()
+    }
+
+    /// Weakly connected component id for every node, treating edges as
+    /// undirected.  Ids are dense (`0..num_components`) and numbered in
+    /// order of each component's lowest node index.
+    #[allow(dead_code)]
+    pub fn weakly_connected_components(&self) -> Vec<usize> {
+        let n = self.adj.len();
+        let mut parent: Vec<usize> = (0..n).collect();
+
+        for (from, edges) in self.adj.iter().enumerate() {
+            for edge in edges {
+                let a = find_root(&mut parent, from);
+                let b = find_root(&mut parent, edge.to);
+                if a != b {
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut ids = vec![usize::MAX; n];
+        let mut next = 0;
+        let mut component = vec![0; n];
+        for (node, slot) in component.iter_mut().enumerate() {
+            let root = find_root(&mut parent, node);
+            if ids[root] == usize::MAX {
+                ids[root] = next;
+                next += 1;
+            }
+            *slot = ids[root];
+        }
+        component
+    }
+
+    /// Number of weakly connected components; 1 means the graph is one
+    /// piece (ignoring edge direction).
+    #[allow(dead_code)]
+    pub fn num_components(&self) -> usize {
+        self.weakly_connected_components()
+            .iter()
+            .max()
+            .map_or(0, |&max| max + 1)
+    }
+
     pub fn label(&self, node: usize) -> &str {
         &self.labels[node]
     }
@@ -41,6 +323,65 @@ impl Graph {
     pub fn node_index(&self, label: &str) -> Option<usize> {
         self.labels.iter().position(|l| l == label)
     }
+
+    /// Render the graph as a Graphviz `digraph`, with node labels and
+    /// edge weights.
+    #[allow(dead_code)]
+    pub fn to_dot(&self) -> String {
+        self.to_dot_highlighted(&[])
+    }
+
+    /// Like [`Graph::to_dot`], but edges between consecutive nodes of
+    /// `path` (e.g. a `dijkstra` result) are drawn in red.  If the path
+    /// steps across parallel edges, every copy is highlighted.
+    #[allow(dead_code)]
+    pub fn to_dot_highlighted(&self, path: &[usize]) -> String {
+        let on_path: HashSet<(usize, usize)> = path.windows(2).map(|w| (w[0], w[1])).collect();
+
+        let mut out = String::from("digraph {\n");
+        for (node, label) in self.labels.iter().enumerate() {
+            let _ = writeln!(out, "    n{} [label=\"{}\"];", node, escape_dot(label));
+        }
+        for (from, edges) in self.adj.iter().enumerate() {
+            for edge in edges {
+                let style = if on_path.contains(&(from, edge.to)) {
+                    ", color=red, penwidth=2"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "    n{} -> n{} [label=\"{}\"{}];",
+                    from, edge.to, edge.weight, style
+                );
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Union-find lookup with path halving.
+fn find_root(parent: &mut [usize], mut node: usize) -> usize {
+    while parent[node] != node {
+        parent[node] = parent[parent[node]];
+        node = parent[node];
+    }
+    node
+}
+
+/// Escape a label for use inside a double-quoted DOT string.
+fn escape_dot(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
 }
 
 /// Add an edge between two nodes.
@@ -63,9 +404,268 @@ pub fn build_benchmark_graph() -> Graph {
 
     connect(&mut g, (0, 1), 2);   // A -- B : 2
     connect(&mut g, (0, 2), 10);  // A -- C : 10
-    connect(&mut g, (3, 1), 1);   // B -- D : 1
+    connect(&mut g, (1, 3), 1);   // B -- D : 1
     connect(&mut g, (3, 4), 4);   // D -- E : 4
     connect(&mut g, (2, 4), 0);   // C -- E : 0
 
     g
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undirected_edge_adds_both_directions() {
+        let mut g = Graph::new(vec!["A", "B", "C"]);
+        g.add_undirected_edge(0, 1, 5);
+        g.add_edge(1, 2, 3);
+
+        assert_eq!(g.num_edges(), 3);
+        assert!(g.neighbors(0).iter().any(|e| e.to == 1 && e.weight == 5));
+        assert!(g.neighbors(1).iter().any(|e| e.to == 0 && e.weight == 5));
+        assert!(g.neighbors(2).is_empty());
+    }
+
+    #[test]
+    fn num_edges_counts_benchmark_graph() {
+        assert_eq!(build_benchmark_graph().num_edges(), 5);
+    }
+
+    #[test]
+    fn remove_edge_drops_first_match_only() {
+        let mut g = Graph::new(vec!["A", "B", "C"]);
+        g.add_edge(0, 1, 4);
+        g.add_edge(0, 2, 1);
+        g.add_edge(0, 1, 9);
+
+        assert!(g.remove_edge(0, 1));
+        let weights: Vec<(usize, u32)> = g.neighbors(0).iter().map(|e| (e.to, e.weight)).collect();
+        assert_eq!(weights, vec![(2, 1), (1, 9)]);
+
+        assert!(g.remove_edge(0, 1));
+        assert!(!g.remove_edge(0, 1));
+        assert!(!g.remove_edge(1, 0));
+        assert_eq!(g.num_edges(), 1);
+    }
+
+    #[test]
+    fn set_edge_weight_updates_first_match_in_place() {
+        let mut g = Graph::new(vec!["A", "B"]);
+        g.add_edge(0, 1, 4);
+        g.add_edge(0, 1, 9);
+
+        assert!(g.set_edge_weight(0, 1, 2));
+        let weights: Vec<u32> = g.neighbors(0).iter().map(|e| e.weight).collect();
+        assert_eq!(weights, vec![2, 9]);
+        assert!(!g.set_edge_weight(1, 0, 2));
+    }
+
+    #[test]
+    fn dijkstra_sees_edge_edits() {
+        let mut g = build_benchmark_graph();
+        assert!(g.set_edge_weight(3, 4, 20));
+        assert_eq!(crate::dijkstra::dijkstra(&g, 0, 4), Ok(Some((10, vec![0, 2, 4]))));
+
+        assert!(g.remove_edge(2, 4));
+        assert_eq!(crate::dijkstra::dijkstra(&g, 0, 4), Ok(Some((23, vec![0, 1, 3, 4]))));
+    }
+
+    #[test]
+    fn to_dot_lists_nodes_and_weighted_edges() {
+        let mut g = Graph::new(vec!["A", "B"]);
+        g.add_edge(0, 1, 3);
+
+        assert_eq!(
+            g.to_dot(),
+            "digraph {\n    n0 [label=\"A\"];\n    n1 [label=\"B\"];\n    n0 -> n1 [label=\"3\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn to_dot_escapes_labels() {
+        let g = Graph::new(vec!["say \"hi\"", "back\\slash"]);
+        let dot = g.to_dot();
+
+        assert!(dot.contains(r#"n0 [label="say \"hi\""];"#));
+        assert!(dot.contains(r#"n1 [label="back\\slash"];"#));
+    }
+
+    #[test]
+    fn to_dot_highlights_path_edges() {
+        let g = build_benchmark_graph();
+        let dot = g.to_dot_highlighted(&[0, 1, 3, 4]);
+
+        assert!(dot.contains("n0 -> n1 [label=\"2\", color=red, penwidth=2];"));
+        assert!(dot.contains("n1 -> n3 [label=\"1\", color=red, penwidth=2];"));
+        assert!(dot.contains("n3 -> n4 [label=\"4\", color=red, penwidth=2];"));
+        assert!(dot.contains("n0 -> n2 [label=\"10\"];"));
+        assert_eq!(dot.matches("color=red").count(), 3);
+    }
+
+    #[test]
+    fn from_edge_list_builds_graph() {
+        let g = Graph::from_edge_list(
+            "# benchmark graph\n\
+             A B 2\n\
+             A C 10\n\
+             \n\
+             B D 1   # shortcut\n\
+             D E 4\n\
+             C E 0\n",
+        )
+        .unwrap();
+
+        assert_eq!(g.num_nodes(), 5);
+        assert_eq!(g.num_edges(), 5);
+        assert_eq!(g.label(3), "D");
+        assert_eq!(g.node_index("E"), Some(4));
+        assert_eq!(crate::dijkstra::dijkstra(&g, 0, 4), Ok(Some((7, vec![0, 1, 3, 4]))));
+    }
+
+    #[test]
+    fn from_edge_list_reports_bad_lines() {
+        let err = Graph::from_edge_list("A B 2\nA C\n").err().unwrap();
+        assert!(err.contains("line 2"), "{}", err);
+        assert!(err.contains("A C"), "{}", err);
+
+        let err = Graph::from_edge_list("A B -1\n").err().unwrap();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(err.contains("-1"), "{}", err);
+
+        assert!(Graph::from_edge_list("A B 2 extra\n").is_err());
+    }
+
+    fn incoming(g: &Graph, u: usize) -> Vec<(usize, u32)> {
+        g.predecessors(u).iter().map(|e| (e.to, e.weight)).collect()
+    }
+
+    #[test]
+    fn predecessors_lists_incoming_edges() {
+        let g = build_benchmark_graph();
+
+        assert_eq!(incoming(&g, 4), vec![(2, 0), (3, 4)]);
+        assert_eq!(incoming(&g, 1), vec![(0, 2)]);
+        assert!(incoming(&g, 0).is_empty());
+    }
+
+    #[test]
+    fn predecessors_follow_edge_changes() {
+        let mut g = build_benchmark_graph();
+        assert_eq!(incoming(&g, 0), vec![]);
+
+        g.add_edge(4, 0, 7);
+        assert_eq!(incoming(&g, 0), vec![(4, 7)]);
+
+        g.set_edge_weight(4, 0, 3);
+        assert_eq!(incoming(&g, 0), vec![(4, 3)]);
+
+        g.remove_edge(4, 0);
+        assert_eq!(incoming(&g, 0), vec![]);
+    }
+
+    #[test]
+    fn benchmark_graph_is_one_component() {
+        let g = build_benchmark_graph();
+        assert_eq!(g.weakly_connected_components(), vec![0; 5]);
+        assert_eq!(g.num_components(), 1);
+    }
+
+    #[test]
+    fn components_ignore_direction_and_find_isolated_nodes() {
+        let mut g = Graph::new(vec!["A", "B", "C", "D", "E", "F"]);
+        g.add_edge(1, 0, 1);
+        g.add_edge(3, 4, 1);
+        g.add_edge(5, 4, 1);
+
+        assert_eq!(g.weakly_connected_components(), vec![0, 0, 1, 2, 2, 2]);
+        assert_eq!(g.num_components(), 3);
+        assert_eq!(Graph::new(Vec::new()).num_components(), 0);
+    }
+
+    #[test]
+    fn add_node_appends_or_returns_existing() {
+        let mut g = build_benchmark_graph();
+        let before = g.predecessors(4).len();
+
+        let f = g.add_node("F");
+        assert_eq!(f, 5);
+        assert_eq!(g.num_nodes(), 6);
+        assert_eq!(g.label(f), "F");
+        assert!(g.neighbors(f).is_empty());
+        assert!(g.predecessors(f).is_empty());
+
+        assert_eq!(g.add_node("F"), f);
+        assert_eq!(g.add_node("C"), 2);
+        assert_eq!(g.num_nodes(), 6);
+
+        g.add_edge(4, f, 1);
+        assert_eq!(g.predecessors(4).len(), before);
+        assert_eq!(crate::dijkstra::dijkstra(&g, 0, f), Ok(Some((8, vec![0, 1, 3, 4, 5]))));
+    }
+
+    #[test]
+    fn edges_round_trip_through_edge_list() {
+        let g = build_benchmark_graph();
+        let edges: Vec<(usize, usize, u32)> = g.edges().collect();
+        assert_eq!(edges, vec![(0, 1, 2), (0, 2, 10), (1, 3, 1), (2, 4, 0), (3, 4, 4)]);
+        assert_eq!(edges.len(), g.num_edges());
+
+        let text: String = g
+            .edges()
+            .map(|(from, to, w)| format!("{} {} {}\n", g.label(from), g.label(to), w))
+            .collect();
+        let parsed = Graph::from_edge_list(&text).unwrap();
+        let relabel = |graph: &Graph| -> Vec<(String, String, u32)> {
+            graph
+                .edges()
+                .map(|(f, t, w)| (graph.label(f).to_string(), graph.label(t).to_string(), w))
+                .collect()
+        };
+        assert_eq!(relabel(&parsed), relabel(&g));
+    }
+
+    #[test]
+    fn degree_and_isolated_nodes() {
+        let mut g = build_benchmark_graph();
+        assert_eq!(g.out_degree(0), 2);
+        assert_eq!(g.out_degree(4), 0);
+        assert!(g.isolated_nodes().is_empty());
+
+        let f = g.add_node("F");
+        let h = g.add_node("H");
+        g.add_node("G");
+        g.add_edge(h, h, 1);
+
+        // E has only incoming edges and H a self-loop; neither is isolated.
+        assert_eq!(g.isolated_nodes(), vec![f, 7]);
+        assert_eq!(g.isolated_labels(), vec!["F", "G"]);
+    }
+
+    #[test]
+    fn collapse_parallel_edges_keeps_minimum_weight() {
+        let mut g = Graph::new(vec!["A", "B", "C"]);
+        g.add_edge(0, 1, 5);
+        g.add_edge(0, 2, 4);
+        g.add_edge(0, 1, 2);
+        g.add_edge(1, 2, 1);
+        assert_eq!(g.predecessors(1).len(), 2);
+
+        g.collapse_parallel_edges();
+
+        assert_eq!(g.edges().collect::<Vec<_>>(), vec![(0, 1, 2), (0, 2, 4), (1, 2, 1)]);
+        assert_eq!(g.predecessors(1).len(), 1);
+    }
+
+    #[test]
+    fn contains_edge_and_edge_weight() {
+        let mut g = build_benchmark_graph();
+        assert!(g.contains_edge(1, 3));
+        assert!(!g.contains_edge(3, 1));
+        assert_eq!(g.edge_weight(0, 2), Some(10));
+        assert_eq!(g.edge_weight(4, 0), None);
+
+        g.add_edge(0, 2, 1);
+        assert_eq!(g.edge_weight(0, 2), Some(10));
+    }
+}
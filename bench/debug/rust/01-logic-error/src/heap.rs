@@ -1,12 +1,12 @@
 /// A binary heap entry storing an item with an associated priority.
 #[derive(Debug, Clone)]
-pub struct HeapEntry {
-    pub priority: u32,
+pub struct HeapEntry<P> {
+    pub priority: P,
     pub item: usize,
 }
 
-impl HeapEntry {
-    pub fn new(item: usize, priority: u32) -> Self {
+impl<P> HeapEntry<P> {
+    pub fn new(item: usize, priority: P) -> Self {
         HeapEntry { priority, item }
     }
 }
@@ -29,10 +29,13 @@ impl PositionMap {
         self.positions.get(item).copied().flatten()
     }
 
+    /// Record `item`'s heap position, growing the map if `item` is past
+    /// the current capacity.
     pub fn set(&mut self, item: usize, pos: usize) {
-        if item < self.positions.len() {
-            self.positions[item] = Some(pos);
+        if item >= self.positions.len() {
+            self.positions.resize(item + 1, None);
         }
+        self.positions[item] = Some(pos);
     }
 
     pub fn remove(&mut self, item: usize) {
@@ -4,19 +4,63 @@ use crate::heap::{HeapEntry, PositionMap};
 ///
 /// Supports insert, extract-min, and decrease-key operations needed
 /// for Dijkstra's shortest-path algorithm.
-pub struct MinPriorityQueue {
-    data: Vec<HeapEntry>,
+///
+/// Priorities only need `PartialOrd`, so floating-point weights work,
+/// but the values actually inserted must be totally ordered among
+/// themselves (no NaN); otherwise the heap order is unspecified.
+///
+/// A queue built with `new_max` orders the other way: `extract_min`
+/// returns the entry with the *highest* priority.
+pub struct MinPriorityQueue<P: PartialOrd + Copy> {
+    data: Vec<HeapEntry<P>>,
     pos: PositionMap,
+    max: bool,
 }
 
-impl MinPriorityQueue {
+/// The original `u32`-priority queue.
+#[allow(dead_code)]
+pub type MinPriorityQueueU32 = MinPriorityQueue<u32>;
+
+impl<P: PartialOrd + Copy> MinPriorityQueue<P> {
+    /// Create an empty min-queue.  `capacity` is only a sizing hint:
+    /// items with larger indices can still be inserted.
     pub fn new(capacity: usize) -> Self {
         MinPriorityQueue {
             data: Vec::with_capacity(capacity),
             pos: PositionMap::new(capacity),
+            max: false,
+        }
+    }
+
+    /// Create a max-priority queue: `extract_min` yields the largest
+    /// priority first, and `decrease_key` moves an item away from the top.
+    #[allow(dead_code)]
+    pub fn new_max(capacity: usize) -> Self {
+        MinPriorityQueue {
+            max: true,
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Build a min-queue from `(item, priority)` pairs in O(n).
+    ///
+    /// Items must be distinct.  The entries are laid out as given and
+    /// then heapified bottom-up, sifting down from the last internal
+    /// node to the root.
+    #[allow(dead_code)]
+    pub fn from_pairs(pairs: Vec<(usize, P)>, capacity: usize) -> Self {
+        let mut pq = Self::new(capacity.max(pairs.len()));
+        for (idx, (item, priority)) in pairs.into_iter().enumerate() {
+            pq.data.push(HeapEntry::new(item, priority));
+            pq.pos.set(item, idx);
+        }
+        for idx in (0..pq.data.len() / 2).rev() {
+            pq.sift_down(idx);
         }
+        pq
     }
 
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
@@ -31,15 +75,22 @@ impl MinPriorityQueue {
     }
 
     /// Insert a new item with the given priority.
-    pub fn insert(&mut self, item: usize, priority: u32) {
+    pub fn insert(&mut self, item: usize, priority: P) {
         let idx = self.data.len();
         self.data.push(HeapEntry::new(item, priority));
         self.pos.set(item, idx);
         self.sift_up(idx);
     }
 
-    /// Remove and return the item with the lowest priority.
-    pub fn extract_min(&mut self) -> Option<HeapEntry> {
+    /// Borrow the entry `extract_min` would return, without removing it.
+    #[allow(dead_code)]
+    pub fn peek(&self) -> Option<&HeapEntry<P>> {
+        self.data.first()
+    }
+
+    /// Remove and return the item with the lowest priority (highest for a
+    /// max queue).
+    pub fn extract_min(&mut self) -> Option<HeapEntry<P>> {
         if self.data.is_empty() {
             return None;
         }
@@ -57,16 +108,67 @@ impl MinPriorityQueue {
         Some(entry)
     }
 
+    /// Consume the queue, returning every entry in extraction order
+    /// (ascending priority; descending for a max queue).
+    #[allow(dead_code)]
+    pub fn into_sorted_vec(mut self) -> Vec<HeapEntry<P>> {
+        self.drain().collect()
+    }
+
+    /// Remove every entry, yielding them in extraction order.
+    ///
+    /// The queue is emptied up front, so it is empty afterwards even if
+    /// the iterator is dropped before it is exhausted.
+    pub fn drain(&mut self) -> impl Iterator<Item = HeapEntry<P>> {
+        let mut sorted = Vec::with_capacity(self.data.len());
+        while let Some(entry) = self.extract_min() {
+            sorted.push(entry);
+        }
+        sorted.into_iter()
+    }
+
     /// Decrease the priority of an existing item.
     /// Panics if the item is not in the queue or the new priority is higher.
-    pub fn decrease_key(&mut self, item: usize, new_priority: u32) {
+    pub fn decrease_key(&mut self, item: usize, new_priority: P) {
         if let Some(idx) = self.pos.get(item) {
             debug_assert!(
                 new_priority <= self.data[idx].priority,
                 "decrease_key called with higher priority"
             );
             self.data[idx].priority = new_priority;
-            self.sift_up(idx);
+            if self.max {
+                self.sift_down(idx);
+            } else {
+                self.sift_up(idx);
+            }
+        }
+    }
+
+    /// Increase the priority of an existing item.
+    /// Does nothing if the item is not in the queue; debug builds panic
+    /// if the new priority is lower.
+    #[allow(dead_code)]
+    pub fn increase_key(&mut self, item: usize, new_priority: P) {
+        if let Some(idx) = self.pos.get(item) {
+            debug_assert!(
+                new_priority >= self.data[idx].priority,
+                "increase_key called with lower priority"
+            );
+            self.data[idx].priority = new_priority;
+            if self.max {
+                self.sift_up(idx);
+            } else {
+                self.sift_down(idx);
+            }
+        }
+    }
+
+    /// Whether the entry at `a` belongs above the entry at `b`.
+    fn outranks(&self, a: usize, b: usize) -> bool {
+        if self.max {
+            self.data[a].priority > self.data[b].priority
+        } else {
+            self.data[a].priority < self.data[b].priority
         }
     }
 
@@ -74,7 +176,7 @@ impl MinPriorityQueue {
     fn sift_up(&mut self, mut idx: usize) {
         while idx > 0 {
             let parent = (idx - 1) / 2;
-            if self.data[idx].priority < self.data[parent].priority {
+            if self.outranks(idx, parent) {
                 self.swap_entries(idx, parent);
                 idx = parent;
             } else {
@@ -91,10 +193,10 @@ impl MinPriorityQueue {
             let right = 2 * idx + 2;
             let mut candidate = idx;
 
-            if left < len && self.data[left].priority < self.data[candidate].priority {
+            if left < len && self.outranks(left, candidate) {
                 candidate = left;
             }
-            if right < len && self.data[right].priority < self.data[candidate].priority {
+            if right < len && self.outranks(right, candidate) {
                 candidate = right;
             }
 
@@ -131,4 +233,142 @@ mod tests {
         let first = pq.extract_min().unwrap();
         println!("Extracted: item={}, priority={}", first.item, first.priority);
     }
+
+    #[test]
+    fn test_min_and_max_extract_order() {
+        let priorities = [5, 1, 9, 3, 7, 3, 0];
+        let mut min = MinPriorityQueue::new(priorities.len());
+        let mut max = MinPriorityQueue::new_max(priorities.len());
+        for (item, &p) in priorities.iter().enumerate() {
+            min.insert(item, p);
+            max.insert(item, p);
+        }
+
+        let drain = |pq: &mut MinPriorityQueue<u32>| {
+            std::iter::from_fn(|| pq.extract_min().map(|e| e.priority)).collect::<Vec<_>>()
+        };
+        assert_eq!(drain(&mut min), vec![0, 1, 3, 3, 5, 7, 9]);
+        assert_eq!(drain(&mut max), vec![9, 7, 5, 3, 3, 1, 0]);
+
+        let mut max = MinPriorityQueue::new_max(3);
+        max.insert(0, 10);
+        max.insert(1, 8);
+        max.insert(2, 6);
+        max.decrease_key(0, 1);
+        assert_eq!(max.extract_min().unwrap().item, 1);
+    }
+
+    #[test]
+    fn test_increase_and_decrease_same_item() {
+        let mut pq = MinPriorityQueue::new(4);
+        pq.insert(0, 1);
+        pq.insert(1, 4);
+        pq.insert(2, 6);
+        pq.insert(3, 8);
+
+        pq.increase_key(0, 7);
+        pq.decrease_key(0, 5);
+        pq.increase_key(0, 9);
+        pq.decrease_key(3, 2);
+        pq.increase_key(42, 100);
+
+        let order: Vec<usize> = std::iter::from_fn(|| pq.extract_min().map(|e| e.item)).collect();
+        assert_eq!(order, vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut pq = MinPriorityQueue::new(2);
+        assert!(pq.peek().is_none());
+
+        pq.insert(0, 5);
+        pq.insert(1, 2);
+        assert_eq!(pq.peek().map(|e| (e.item, e.priority)), Some((1, 2)));
+        assert_eq!(pq.len(), 2);
+        assert!(pq.contains(1));
+        assert_eq!(pq.extract_min().unwrap().item, 1);
+    }
+
+    #[test]
+    fn test_from_pairs_matches_insert() {
+        let pairs: Vec<(usize, u32)> = vec![(0, 8), (1, 3), (2, 9), (3, 1), (4, 6), (5, 2), (6, 7)];
+
+        let mut built = MinPriorityQueue::from_pairs(pairs.clone(), 7);
+        let mut inserted = MinPriorityQueue::new(7);
+        for &(item, p) in &pairs {
+            inserted.insert(item, p);
+        }
+
+        built.decrease_key(2, 0);
+        inserted.decrease_key(2, 0);
+
+        let drain = |pq: &mut MinPriorityQueue<u32>| {
+            std::iter::from_fn(|| pq.extract_min().map(|e| (e.item, e.priority))).collect::<Vec<_>>()
+        };
+        let expected = drain(&mut inserted);
+        assert_eq!(expected[0], (2, 0));
+        assert_eq!(drain(&mut built), expected);
+    }
+
+    #[test]
+    fn test_generic_priorities() {
+        let mut pq: MinPriorityQueue<f64> = MinPriorityQueue::new(3);
+        pq.insert(0, 2.5);
+        pq.insert(1, 0.25);
+        pq.insert(2, 1.0);
+        pq.decrease_key(0, 0.5);
+        let order: Vec<usize> = std::iter::from_fn(|| pq.extract_min().map(|e| e.item)).collect();
+        assert_eq!(order, vec![1, 0, 2]);
+
+        let mut pq: MinPriorityQueue<u64> = MinPriorityQueue::new(2);
+        pq.insert(0, u64::MAX);
+        pq.insert(1, u64::from(u32::MAX) + 1);
+        assert_eq!(pq.extract_min().unwrap().item, 1);
+    }
+
+    #[test]
+    fn test_into_sorted_vec_and_drain() {
+        let priorities: Vec<u32> = vec![42, 7, 19, 7, 0, 88, 23, 5, 61, 5, 13];
+        let fill = |pq: &mut MinPriorityQueue<u32>| {
+            for (item, &p) in priorities.iter().enumerate() {
+                pq.insert(item, p);
+            }
+        };
+
+        let mut pq = MinPriorityQueue::new(priorities.len());
+        fill(&mut pq);
+        let sorted = pq.into_sorted_vec();
+        assert_eq!(sorted.len(), priorities.len());
+        assert!(sorted.windows(2).all(|w| w[0].priority <= w[1].priority));
+
+        let mut pq = MinPriorityQueue::new(priorities.len());
+        fill(&mut pq);
+        let first: Vec<u32> = pq.drain().take(2).map(|e| e.priority).collect();
+        assert_eq!(first, vec![0, 5]);
+        assert!(pq.is_empty());
+        assert!(!pq.contains(0));
+
+        fill(&mut pq);
+        let drained: Vec<u32> = pq.drain().map(|e| e.priority).collect();
+        assert!(drained.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(pq.len(), 0);
+    }
+
+    #[test]
+    fn test_items_beyond_initial_capacity() {
+        let mut pq = MinPriorityQueue::new(2);
+        pq.insert(0, 10);
+        pq.insert(5, 20);
+        pq.insert(100, 30);
+
+        assert!(pq.contains(5));
+        assert!(pq.contains(100));
+        assert!(!pq.contains(50));
+
+        pq.decrease_key(100, 1);
+        pq.decrease_key(5, 2);
+        let order: Vec<usize> = std::iter::from_fn(|| pq.extract_min().map(|e| e.item)).collect();
+        assert_eq!(order, vec![100, 5, 0]);
+        assert!(!pq.contains(100));
+    }
 }
@@ -1,63 +1,501 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::hash::Hash;
+use std::time::{Duration, Instant};
 
+use crate::entry::CacheEntry;
 use crate::lru::DoublyLinkedList;
 
+/// The boxed closure types a cache stores for its weigher and eviction
+/// callback.
+///
+/// [`LocalCallbacks`], the default, accepts any closure.  `SyncLruCache`
+/// uses a kind whose closures are `Send`, which makes its inner cache
+/// `Send` whenever the keys and values are.
+pub trait Callbacks<K, V> {
+    /// Computes the weight an entry counts against the cache capacity.
+    type Weigher: ?Sized + Fn(&K, &V) -> usize;
+    /// Receives entries evicted for capacity.
+    type OnEvict: ?Sized + FnMut(K, V);
+}
+
+/// Callbacks with no `Send` bound, for a cache used on one thread.
+pub struct LocalCallbacks;
+
+impl<K, V> Callbacks<K, V> for LocalCallbacks {
+    type Weigher = dyn Fn(&K, &V) -> usize;
+    type OnEvict = dyn FnMut(K, V);
+}
+
+/// How the cache chooses which entry to evict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Exact LRU: every access relinks the entry to the front of the list.
+    #[default]
+    Lru,
+    /// CLOCK (second chance), an approximation of LRU.
+    ///
+    /// An access only sets the entry's reference bit.  To evict, a hand
+    /// sweeps the node slots circularly, clearing set bits, and takes
+    /// the first entry whose bit was already clear.  An entry touched
+    /// since the hand last passed survives one more sweep, so victims
+    /// are "not recently used" rather than strictly least recently used.
+    /// The list keeps insertion order, which is what `iter`, `peek_lru`
+    /// and `pop_lru` follow under this policy.
+    #[allow(dead_code)]
+    Clock,
+}
+
+/// Capacity used by `LruCacheBuilder::default` and `LRUCache::default`.
+pub const DEFAULT_CAPACITY: usize = 128;
+
 /// A fixed-capacity LRU (Least Recently Used) cache.
 ///
 /// Combines a `HashMap` for O(1) key lookup with an index-based doubly
 /// linked list that maintains access order so the least-recently-used
 /// entry can be evicted in O(1).
-pub struct LRUCache<K: Clone + Eq + Hash + std::fmt::Debug, V: Clone + std::fmt::Debug> {
+///
+/// Capacity is a budget of total entry weight.  Every entry weighs 1
+/// unless the cache is built with [`LRUCache::with_weigher`], so by
+/// default the capacity is simply the maximum number of entries.
+pub struct LRUCache<K: Clone + Eq + Hash, V: Clone, C: Callbacks<K, V> = LocalCallbacks> {
     capacity: usize,
     map: HashMap<K, usize>, // key -> node index in the list
     list: DoublyLinkedList<K, V>,
+    on_evict: Option<Box<C::OnEvict>>,
+    weigher: Option<Box<C::Weigher>>,
+    current_weight: usize,
+    policy: EvictionPolicy,
+    hand: usize, // CLOCK sweep position in the node slots
+    default_ttl: Option<Duration>,
 }
 
-impl<K: Clone + Eq + Hash + std::fmt::Debug, V: Clone + PartialEq + std::fmt::Debug> LRUCache<K, V> {
+impl<K: Clone + Eq + Hash, V: Clone> LRUCache<K, V> {
     pub fn new(capacity: usize) -> Self {
-        assert!(capacity > 0, "cache capacity must be > 0");
-        LRUCache {
-            capacity,
-            map: HashMap::with_capacity(capacity),
-            list: DoublyLinkedList::new(),
-        }
+        Self::builder().capacity(capacity).build()
+    }
+
+    /// Start configuring a cache.  See [`LruCacheBuilder`].
+    pub fn builder() -> LruCacheBuilder<K, V> {
+        LruCacheBuilder::default()
+    }
+
+    /// Create a cache that evicts according to `policy`.
+    #[allow(dead_code)]
+    pub fn with_policy(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self::builder().capacity(capacity).policy(policy).build()
+    }
+
+    /// Create a cache bounded by the total weight of its entries.
+    ///
+    /// `weigher` computes each entry's weight once on insert.  `put`
+    /// evicts least-recently-used entries until the new entry fits.  An
+    /// entry heavier than `max_weight` on its own is rejected: it is not
+    /// stored, and any previous value for the same key is removed.
+    #[allow(dead_code)]
+    pub fn with_weigher(max_weight: usize, weigher: impl Fn(&K, &V) -> usize + 'static) -> Self {
+        Self::builder().capacity(max_weight).weigher(weigher).build()
+    }
+
+    /// Register a callback invoked with the owned key and value of every
+    /// entry evicted for capacity.
+    ///
+    /// The callback fires when `put` (or a shrinking `set_capacity`)
+    /// drops the least-recently-used entry.  During `put` it runs before
+    /// the new entry is inserted.  In-place updates, `remove`, `pop_lru`
+    /// and TTL expiry do not trigger it.
+    #[allow(dead_code)]
+    pub fn on_evict(self, f: impl FnMut(K, V) + 'static) -> Self {
+        self.boxed_on_evict(Box::new(f))
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone, C: Callbacks<K, V>> LRUCache<K, V, C> {
+    /// [`LRUCache::on_evict`] for a callback that is already boxed.
+    pub(crate) fn boxed_on_evict(mut self, f: Box<C::OnEvict>) -> Self {
+        self.on_evict = Some(f);
+        self
     }
 
     /// Retrieve a value by key, marking it as most-recently-used.
     ///
-    /// Returns `None` on a cache miss.
+    /// Returns `None` on a cache miss.  An entry whose TTL has elapsed
+    /// counts as a miss and is removed from the cache.
     pub fn get(&mut self, key: &K) -> Option<&V> {
         if let Some(&idx) = self.map.get(key) {
-            // Move to front = most recently used.
-            self.list.move_to_front(idx);
+            if self.list.is_expired(idx, Instant::now()) {
+                self.remove_index(idx);
+                return None;
+            }
+            self.touch(idx);
             self.list.get_value(idx)
         } else {
             None
         }
     }
 
+    /// Mutably borrow a value by key, marking it as most-recently-used.
+    ///
+    /// Expiry is handled as in `get`.  The entry's weight is not
+    /// recomputed after mutation.
+    #[allow(dead_code)]
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let &idx = self.map.get(key)?;
+        if self.list.is_expired(idx, Instant::now()) {
+            self.remove_index(idx);
+            return None;
+        }
+        self.touch(idx);
+        self.list.get_value_mut(idx)
+    }
+
+    /// Look up a value without marking it as most-recently-used.
+    ///
+    /// Expired entries read as a miss but are left in place, since
+    /// `peek` cannot mutate the cache.
+    #[allow(dead_code)]
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let &idx = self.map.get(key)?;
+        if self.list.is_expired(idx, Instant::now()) {
+            return None;
+        }
+        self.list.get_value(idx)
+    }
+
+    /// Borrow the entry that would be evicted next, without reordering.
+    #[allow(dead_code)]
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        self.list.peek_tail()
+    }
+
     /// Insert or update a key-value pair.
     ///
     /// If the cache is full, the least-recently-used entry is evicted.
+    /// The entry expires after the cache's default TTL, if one was
+    /// configured, and otherwise never; updating a key replaces any
+    /// previous deadline.
     pub fn put(&mut self, key: K, value: V) {
+        self.insert(key, value, self.default_deadline());
+    }
+
+    /// Return the value for `key`, inserting the result of `f` on a miss.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache has a weigher and the new value alone exceeds
+    /// the capacity, since nothing is then stored to borrow.
+    #[allow(dead_code)]
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &V {
+        match self.get_or_try_insert_with(key, || Ok::<V, Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Return the value for `key`, inserting `default` on a miss.
+    ///
+    /// On a hit `default` is dropped unused; prefer `get_or_insert_with`
+    /// when the value is costly to build.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same condition as `get_or_insert_with`.
+    #[allow(dead_code)]
+    pub fn get_or_insert(&mut self, key: K, default: V) -> &V {
+        self.get_or_insert_with(key, || default)
+    }
+
+    /// Return the value for `key`, inserting the result of a fallible
+    /// factory on a miss.
+    ///
+    /// `f` only runs on a miss (including an expired entry).  If it
+    /// returns `Err`, the error is propagated and the cache is left
+    /// exactly as it was: nothing is evicted, reordered or removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same condition as `get_or_insert_with`.
+    #[allow(dead_code)]
+    pub fn get_or_try_insert_with<E>(
+        &mut self,
+        key: K,
+        f: impl FnOnce() -> Result<V, E>,
+    ) -> Result<&V, E> {
+        let now = Instant::now();
+        let live = self
+            .map
+            .get(&key)
+            .copied()
+            .filter(|&idx| !self.list.is_expired(idx, now));
+
+        let idx = match live {
+            Some(idx) => {
+                self.touch(idx);
+                idx
+            }
+            None => {
+                let value = f()?;
+                let expires_at = self.default_deadline();
+                self.insert(key, value, expires_at)
+                    .expect("value exceeds the cache's total weight capacity")
+            }
+        };
+        Ok(self.list.get_value(idx).expect("index of a stored entry"))
+    }
+
+    /// Insert a batch of key-value pairs in order.
+    ///
+    /// The final state always equals calling `put` for each pair.  For a
+    /// count-bounded LRU cache with no eviction callback, pairs that later
+    /// ones would certainly evict are skipped instead of being inserted
+    /// and then evicted.
+    #[allow(dead_code)]
+    pub fn extend(&mut self, iter: impl IntoIterator<Item = (K, V)>) {
+        let items: Vec<(K, V)> = iter.into_iter().collect();
+        let start = if self.policy == EvictionPolicy::Lru
+            && self.weigher.is_none()
+            && self.on_evict.is_none()
+        {
+            Self::surviving_suffix(&items, self.capacity)
+        } else {
+            0
+        };
+        for (key, value) in items.into_iter().skip(start) {
+            self.put(key, value);
+        }
+    }
+
+    /// Start of the shortest suffix of `items` that touches `capacity`
+    /// distinct keys.  Everything before it is evicted or overwritten by
+    /// the time the suffix has been inserted.
+    fn surviving_suffix(items: &[(K, V)], capacity: usize) -> usize {
+        if items.len() <= capacity {
+            return 0;
+        }
+        let mut seen = HashSet::with_capacity(capacity);
+        for (i, (key, _)) in items.iter().enumerate().rev() {
+            seen.insert(key);
+            if seen.len() == capacity {
+                return i;
+            }
+        }
+        0
+    }
+
+    /// Insert or update a key-value pair that expires after `ttl`.
+    ///
+    /// Once the deadline passes, `get` treats the entry as a miss.
+    #[allow(dead_code)]
+    pub fn put_with_ttl(&mut self, key: K, value: V, ttl: Duration) {
+        self.insert(key, value, Some(Instant::now() + ttl));
+    }
+
+    /// Remove every expired entry and return how many were dropped.
+    #[allow(dead_code)]
+    pub fn purge_expired(&mut self) -> usize {
+        let expired = self.list.expired_indices(Instant::now());
+        for &idx in &expired {
+            self.remove_index(idx);
+        }
+        expired.len()
+    }
+
+    /// Store `value` under `key` and return its node index, or `None` if
+    /// the weigher rejected it as oversized.
+    fn insert(&mut self, key: K, value: V, expires_at: Option<Instant>) -> Option<usize> {
+        let weight = self.weigher.as_ref().map_or(1, |w| w(&key, &value));
+
+        // Oversized entries can never fit; reject rather than flush.
+        if weight > self.capacity {
+            self.remove(&key);
+            return None;
+        }
+
         // If the key already exists, update in place.
         if let Some(&idx) = self.map.get(&key) {
+            self.current_weight = self.current_weight - self.list.weight(idx) + weight;
             self.list.update_value(idx, value);
-            self.list.move_to_front(idx);
-            return;
+            self.list.set_weight(idx, weight);
+            self.list.set_expires_at(idx, expires_at);
+            self.touch(idx);
+            while self.current_weight > self.capacity {
+                self.evict_lru(Some(idx));
+            }
+            return Some(idx);
         }
 
-        // Evict LRU if at capacity.
-        if self.map.len() >= self.capacity {
-            if let Some(evicted_key) = self.list.remove_tail() {
-                self.map.remove(&evicted_key);
-            }
+        // Evict LRU entries until the new one fits.
+        while self.current_weight + weight > self.capacity {
+            self.evict_lru(None);
         }
 
         // Insert new entry at the front.
         let idx = self.list.push_front(key.clone(), value);
+        self.list.set_weight(idx, weight);
+        self.list.set_expires_at(idx, expires_at);
         self.map.insert(key, idx);
+        self.current_weight += weight;
+        Some(idx)
+    }
+
+    /// Deadline for an entry stored now under the default TTL.
+    fn default_deadline(&self) -> Option<Instant> {
+        self.default_ttl.map(|ttl| Instant::now() + ttl)
+    }
+
+    /// Remove and return the least-recently-used entry.
+    ///
+    /// Returns `None` if the cache is empty.
+    #[allow(dead_code)]
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let node = self.list.pop_tail()?;
+        self.forget(&node);
+        Some((node.key, node.value))
+    }
+
+    /// Remove `key` from the cache and return its value.
+    ///
+    /// Returns `None` if the key is not present.
+    #[allow(dead_code)]
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let &idx = self.map.get(key)?;
+        let node = self.list.remove_node(idx)?;
+        self.forget(&node);
+        Some(node.value)
+    }
+
+    /// Record an access to the entry at `idx` according to the policy.
+    fn touch(&mut self, idx: usize) {
+        match self.policy {
+            // Move to front = most recently used.
+            EvictionPolicy::Lru => self.list.move_to_front(idx),
+            EvictionPolicy::Clock => self.list.set_referenced(idx),
+        }
+    }
+
+    /// Drop one entry for capacity, chosen by the policy, and notify the
+    /// eviction callback.
+    ///
+    /// The entry at `keep` is never chosen: an update that grows an
+    /// entry's weight must not evict the entry itself.  Under LRU it has
+    /// just moved to the front, so only Clock needs to be told.
+    fn evict_lru(&mut self, keep: Option<usize>) {
+        let victim = match self.policy {
+            EvictionPolicy::Lru => self.list.pop_tail(),
+            EvictionPolicy::Clock => self
+                .list
+                .clock_victim(&mut self.hand, keep)
+                .and_then(|idx| self.list.remove_node(idx)),
+        };
+        if let Some(node) = victim {
+            self.forget(&node);
+            if let Some(ref mut f) = self.on_evict {
+                f(node.key, node.value);
+            }
+        }
+    }
+
+    /// Unlink the node at `idx` and drop its map entry.
+    fn remove_index(&mut self, idx: usize) {
+        if let Some(node) = self.list.remove_node(idx) {
+            self.forget(&node);
+        }
+    }
+
+    /// Drop the map entry and weight of a node already unlinked from the
+    /// list.
+    fn forget(&mut self, node: &CacheEntry<K, V>) {
+        self.map.remove(&node.key);
+        self.current_weight -= node.weight;
+    }
+
+    /// Remove every entry.  Node storage stays allocated for reuse, and
+    /// the eviction callback is not invoked.
+    #[allow(dead_code)]
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.list.clear();
+        self.current_weight = 0;
+        self.hand = 0;
+    }
+
+    /// Release node slots left behind by evictions and removals.
+    ///
+    /// Compacts the list to its live entries and remaps every key to its
+    /// new node index.  Recency order is preserved.
+    #[allow(dead_code)]
+    pub fn shrink_to_fit(&mut self) {
+        self.list.shrink_to_fit();
+        self.hand = 0;
+        for (idx, (key, _)) in self.list.iter().enumerate() {
+            if let Some(slot) = self.map.get_mut(key) {
+                *slot = idx;
+            }
+        }
+        self.map.shrink_to_fit();
+    }
+
+    /// Approximate heap bytes used by the cache's own storage.
+    ///
+    /// Sums the node Vec and free-list capacities and the key map's
+    /// buckets.  The map is estimated as one key, one index and one
+    /// control byte per slot of its reported capacity, which
+    /// undercounts its real bucket array slightly.  Heap data owned by
+    /// keys and values is ignored; see `memory_estimate_with`.
+    #[allow(dead_code)]
+    pub fn memory_estimate(&self) -> usize {
+        let map_slot = std::mem::size_of::<K>() + std::mem::size_of::<usize>() + 1;
+        self.list.heap_bytes() + self.map.capacity() * map_slot
+    }
+
+    /// Like `memory_estimate`, plus `value_bytes` summed over every
+    /// stored value to account for heap data the values own.
+    #[allow(dead_code)]
+    pub fn memory_estimate_with(&self, value_bytes: impl Fn(&V) -> usize) -> usize {
+        self.memory_estimate() + self.iter().map(|(_, v)| value_bytes(v)).sum::<usize>()
+    }
+
+    /// Keys in list order, head (MRU) to tail (LRU).
+    #[cfg(any(test, feature = "debug-internals"))]
+    #[allow(dead_code)]
+    pub fn debug_order(&self) -> Vec<K> {
+        self.list.iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    /// Check that the list links are mutually consistent and that every
+    /// map entry points at a node holding the same key.
+    #[cfg(any(test, feature = "debug-internals"))]
+    #[allow(dead_code)]
+    pub fn validate_links(&self) -> bool {
+        self.list.validate_links()
+            && self.list.len() == self.map.len()
+            && self
+                .map
+                .iter()
+                .all(|(k, &idx)| self.list.get_key(idx) == Some(k))
+    }
+
+    /// Iterate entries from most- to least-recently-used.
+    ///
+    /// Iteration does not count as access and leaves the order unchanged.
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter()
+    }
+
+    /// Mutably iterate entries from most- to least-recently-used.
+    ///
+    /// Like `iter`, this leaves the order unchanged.  Entry weights are
+    /// not recomputed after mutation.
+    #[allow(dead_code)]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.list.iter_mut()
+    }
+
+    /// Iterate entries from least- to most-recently-used.
+    #[allow(dead_code)]
+    pub fn iter_lru_first(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter_rev()
     }
 
     /// Check whether `key` is present without affecting access order.
@@ -66,9 +504,680 @@ impl<K: Clone + Eq + Hash + std::fmt::Debug, V: Clone + PartialEq + std::fmt::De
         self.map.contains_key(key)
     }
 
+    /// Maximum total weight (entry count, unless weighted) the cache
+    /// holds before evicting.
+    #[allow(dead_code)]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Total weight of the entries currently stored.
+    #[allow(dead_code)]
+    pub fn current_weight(&self) -> usize {
+        self.current_weight
+    }
+
+    /// Change the capacity at runtime.
+    ///
+    /// Shrinking below the current weight evicts least-recently-used
+    /// entries until the cache fits.
+    #[allow(dead_code)]
+    pub fn set_capacity(&mut self, new_cap: usize) {
+        assert!(new_cap > 0, "cache capacity must be > 0");
+        while self.current_weight > new_cap {
+            self.evict_lru(None);
+        }
+        self.capacity = new_cap;
+    }
+
     /// Current number of entries.
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
         self.map.len()
     }
 }
+
+impl<K: Clone + Eq + Hash, V: Clone> Default for LRUCache<K, V> {
+    /// An LRU cache holding up to `DEFAULT_CAPACITY` entries.
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Chained configuration for an `LRUCache`.
+///
+/// Defaults to `DEFAULT_CAPACITY` entries, the LRU policy, no TTL, no
+/// weigher and no eviction callback.
+pub struct LruCacheBuilder<K, V, C: Callbacks<K, V> = LocalCallbacks> {
+    capacity: usize,
+    policy: EvictionPolicy,
+    ttl: Option<Duration>,
+    on_evict: Option<Box<C::OnEvict>>,
+    weigher: Option<Box<C::Weigher>>,
+}
+
+impl<K, V, C: Callbacks<K, V>> Default for LruCacheBuilder<K, V, C> {
+    fn default() -> Self {
+        LruCacheBuilder {
+            capacity: DEFAULT_CAPACITY,
+            policy: EvictionPolicy::default(),
+            ttl: None,
+            on_evict: None,
+            weigher: None,
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> LruCacheBuilder<K, V> {
+    /// Eviction callback.  See [`LRUCache::on_evict`].
+    #[allow(dead_code)]
+    pub fn on_evict(self, f: impl FnMut(K, V) + 'static) -> Self {
+        self.boxed_on_evict(Box::new(f))
+    }
+
+    /// Entry weigher.  See [`LRUCache::with_weigher`].
+    pub fn weigher(self, weigher: impl Fn(&K, &V) -> usize + 'static) -> Self {
+        self.boxed_weigher(Box::new(weigher))
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone, C: Callbacks<K, V>> LruCacheBuilder<K, V, C> {
+    /// Maximum number of entries, or maximum total weight if a weigher
+    /// is set.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Eviction policy.  See [`EvictionPolicy`].
+    pub fn policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Default TTL applied by `put`.  `put_with_ttl` still overrides it.
+    #[allow(dead_code)]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// [`LruCacheBuilder::on_evict`] for a callback that is already boxed.
+    pub(crate) fn boxed_on_evict(mut self, f: Box<C::OnEvict>) -> Self {
+        self.on_evict = Some(f);
+        self
+    }
+
+    /// [`LruCacheBuilder::weigher`] for a weigher that is already boxed.
+    pub(crate) fn boxed_weigher(mut self, weigher: Box<C::Weigher>) -> Self {
+        self.weigher = Some(weigher);
+        self
+    }
+
+    /// Build the cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    pub fn build(self) -> LRUCache<K, V, C> {
+        assert!(self.capacity > 0, "cache capacity must be > 0");
+        // A weight budget says nothing about the entry count.
+        let map = if self.weigher.is_some() {
+            HashMap::new()
+        } else {
+            HashMap::with_capacity(self.capacity)
+        };
+        LRUCache {
+            capacity: self.capacity,
+            map,
+            list: DoublyLinkedList::new(),
+            on_evict: self.on_evict,
+            weigher: self.weigher,
+            current_weight: 0,
+            policy: self.policy,
+            hand: 0,
+            default_ttl: self.ttl,
+        }
+    }
+}
+
+/// Formats entries as a map in MRU to LRU order.
+impl<K, V, C> std::fmt::Debug for LRUCache<K, V, C>
+where
+    K: Clone + Eq + Hash + std::fmt::Debug,
+    V: Clone + std::fmt::Debug,
+    C: Callbacks<K, V>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Persists the cache as its capacity plus `(key, value)` pairs in MRU to
+/// LRU order.  Node indices, TTL deadlines, the weigher and the eviction
+/// callback are not serialized; a deserialized cache is count-bounded.
+#[cfg(feature = "serde")]
+impl<K, V, C> serde::Serialize for LRUCache<K, V, C>
+where
+    K: Clone + Eq + Hash + serde::Serialize,
+    V: Clone + serde::Serialize,
+    C: Callbacks<K, V>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("LRUCache", 2)?;
+        state.serialize_field("capacity", &self.capacity)?;
+        state.serialize_field("entries", &self.iter().collect::<Vec<_>>())?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for LRUCache<K, V>
+where
+    K: Clone + Eq + Hash + serde::Deserialize<'de>,
+    V: Clone + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "LRUCache")]
+        struct Snapshot<K, V> {
+            capacity: usize,
+            entries: Vec<(K, V)>,
+        }
+
+        let snapshot = Snapshot::<K, V>::deserialize(deserializer)?;
+        if snapshot.capacity == 0 {
+            return Err(serde::de::Error::custom("cache capacity must be > 0"));
+        }
+
+        // Rebuild from the LRU end so the first entry ends up at the head.
+        let mut cache = LRUCache::new(snapshot.capacity);
+        for (key, value) in snapshot.entries.into_iter().rev() {
+            cache.put(key, value);
+        }
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_entry_is_a_miss_and_frees_its_slot() {
+        let mut cache = LRUCache::new(4);
+        cache.put("a", 1);
+        cache.put_with_ttl("b", 2, Duration::ZERO);
+        cache.put("c", 3);
+        assert_eq!(cache.get(&"a"), Some(&1));
+
+        let b_idx = cache.map[&"b"];
+        assert_eq!(cache.get(&"b"), None);
+        assert!(!cache.contains(&"b"));
+        assert_eq!(cache.len(), 2);
+
+        cache.put("d", 4);
+        assert_eq!(cache.map[&"d"], b_idx);
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn purge_expired_counts_dropped_entries() {
+        let mut cache = LRUCache::new(4);
+        cache.put_with_ttl("a", 1, Duration::ZERO);
+        cache.put("b", 2);
+        cache.put_with_ttl("c", 3, Duration::ZERO);
+        cache.put_with_ttl("d", 4, Duration::from_secs(3600));
+
+        assert_eq!(cache.purge_expired(), 2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.get(&"d"), Some(&4));
+    }
+
+    #[test]
+    fn pop_lru_drains_coldest_first() {
+        let mut cache = LRUCache::new(3);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        cache.get(&"a");
+
+        assert_eq!(cache.pop_lru(), Some(("b", 2)));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(&"b"));
+        assert_eq!(cache.pop_lru(), Some(("c", 3)));
+        assert_eq!(cache.pop_lru(), Some(("a", 1)));
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.pop_lru(), None);
+    }
+
+    #[test]
+    fn set_capacity_evicts_when_shrinking() {
+        let mut cache = LRUCache::new(4);
+        for (i, k) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.put(k, i);
+        }
+        cache.get(&"a");
+
+        cache.set_capacity(2);
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&"a") && cache.contains(&"d"));
+
+        cache.set_capacity(3);
+        cache.put("e", 4);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn peek_does_not_change_eviction_order() {
+        let mut cache = LRUCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+
+        assert_eq!(cache.peek(&"a"), Some(&1));
+        assert_eq!(cache.peek_lru(), Some((&"a", &1)));
+
+        cache.put("c", 3);
+        assert!(!cache.contains(&"a"));
+        assert_eq!(cache.peek_lru(), Some((&"b", &2)));
+    }
+
+    #[test]
+    fn remove_returns_value_and_frees_slot() {
+        let mut cache = LRUCache::new(3);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+
+        let b_idx = cache.map[&"b"];
+        assert_eq!(cache.remove(&"b"), Some(2));
+        assert_eq!(cache.remove(&"b"), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.list.len(), 2);
+
+        cache.put("d", 4);
+        assert_eq!(cache.map[&"d"], b_idx);
+        assert_eq!(cache.pop_lru(), Some(("a", 1)));
+    }
+
+    #[test]
+    fn iter_walks_recency_order_both_ways() {
+        let mut cache = LRUCache::new(3);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        cache.get(&"a");
+
+        let mru: Vec<_> = cache.iter().map(|(k, _)| *k).collect();
+        assert_eq!(mru, ["a", "c", "b"]);
+        let lru: Vec<_> = cache.iter_lru_first().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(lru, [("b", 2), ("c", 3), ("a", 1)]);
+    }
+
+    #[test]
+    fn on_evict_fires_only_for_capacity_eviction() {
+        use std::sync::{Arc, Mutex};
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&evicted);
+        let mut cache = LRUCache::new(2).on_evict(move |k, v| sink.lock().unwrap().push((k, v)));
+
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("a", 10);
+        cache.remove(&"b");
+        assert!(evicted.lock().unwrap().is_empty());
+
+        cache.put("c", 3);
+        cache.put("d", 4);
+        assert_eq!(*evicted.lock().unwrap(), [("a", 10)]);
+    }
+
+    #[test]
+    fn weigher_evicts_until_new_entry_fits() {
+        let mut cache = LRUCache::with_weigher(10, |_: &&str, v: &Vec<u8>| v.len());
+        cache.put("a", vec![0; 4]);
+        cache.put("b", vec![0; 4]);
+        assert_eq!(cache.current_weight(), 8);
+
+        cache.put("c", vec![0; 5]);
+        assert!(!cache.contains(&"a"));
+        assert_eq!(cache.current_weight(), 9);
+
+        cache.put("b", vec![0; 6]);
+        assert!(!cache.contains(&"c"));
+        assert_eq!(cache.current_weight(), 6);
+    }
+
+    #[test]
+    fn weigher_rejects_oversized_entry() {
+        let mut cache = LRUCache::with_weigher(10, |_: &&str, v: &Vec<u8>| v.len());
+        cache.put("a", vec![0; 3]);
+        cache.put("b", vec![0; 3]);
+
+        cache.put("a", vec![0; 11]);
+        assert!(!cache.contains(&"a"));
+        assert_eq!(cache.peek(&"b"), Some(&vec![0; 3]));
+        assert_eq!(cache.current_weight(), 3);
+    }
+
+    #[test]
+    fn get_mut_updates_in_place_and_marks_mru() {
+        let mut cache = LRUCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+
+        *cache.get_mut(&"a").unwrap() += 10;
+        assert!(cache.get_mut(&"z").is_none());
+
+        cache.put("c", 3);
+        assert!(!cache.contains(&"b"));
+        assert_eq!(cache.get(&"a"), Some(&11));
+    }
+
+    #[test]
+    fn clear_empties_cache() {
+        let mut cache = LRUCache::new(3);
+        cache.put("a", 1);
+        cache.put("b", 2);
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.current_weight(), 0);
+        assert_eq!(cache.get(&"a"), None);
+
+        cache.put("c", 3);
+        assert_eq!(cache.pop_lru(), Some(("c", 3)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_preserves_recency_order() {
+        let mut cache = LRUCache::new(3);
+        cache.put("a".to_string(), 1);
+        cache.put("b".to_string(), 2);
+        cache.put("c".to_string(), 3);
+        cache.get(&"a".to_string());
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let restored: LRUCache<String, i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.capacity(), 3);
+        let before: Vec<_> = cache.iter().collect();
+        let after: Vec<_> = restored.iter().collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn shrink_to_fit_keeps_order_and_lookups() {
+        let mut cache = LRUCache::new(8);
+        for (i, k) in ["a", "b", "c", "d", "e", "f", "g", "h"].into_iter().enumerate() {
+            cache.put(k, i);
+        }
+        for k in ["b", "d", "e", "g"] {
+            cache.remove(&k);
+        }
+        cache.get(&"a");
+        let before: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+
+        cache.shrink_to_fit();
+
+        let after: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(before, after);
+        assert_eq!(cache.get(&"c"), Some(&2));
+        assert_eq!(cache.pop_lru(), Some(("f", 5)));
+        cache.put("z", 26);
+        assert_eq!(cache.peek(&"z"), Some(&26));
+    }
+
+    #[test]
+    fn works_without_debug_values() {
+        #[derive(Clone)]
+        struct Opaque(u8);
+
+        let mut cache = LRUCache::new(1);
+        cache.put("a", Opaque(1));
+        cache.put("b", Opaque(2));
+        assert_eq!(cache.get(&"b").map(|v| v.0), Some(2));
+        assert_eq!(format!("{:?}", LRUCache::<u8, u8>::new(1)), "{}");
+    }
+
+    #[test]
+    fn extend_matches_sequential_put() {
+        let batches: [&[(&str, i32)]; 3] = [
+            &[("x", 0), ("y", 1)],
+            &[("a", 1), ("b", 2), ("c", 3), ("a", 4), ("d", 5), ("e", 6), ("c", 7)],
+            &[("a", 1), ("b", 2), ("b", 3), ("b", 4), ("c", 5), ("d", 6)],
+        ];
+        for batch in batches {
+            let mut naive = LRUCache::new(3);
+            let mut batched = LRUCache::new(3);
+            for cache in [&mut naive, &mut batched] {
+                cache.put("old", -1);
+                cache.put("c", -2);
+            }
+
+            for &(k, v) in batch {
+                naive.put(k, v);
+            }
+            batched.extend(batch.iter().copied());
+
+            let expected: Vec<_> = naive.iter().collect();
+            let actual: Vec<_> = batched.iter().collect();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn clock_gives_referenced_entries_a_second_chance() {
+        use std::sync::{Arc, Mutex};
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&evicted);
+        let mut cache = LRUCache::with_policy(3, EvictionPolicy::Clock)
+            .on_evict(move |k, _| sink.lock().unwrap().push(k));
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+
+        // Access sets the reference bit without relinking.
+        cache.get(&"a");
+        let order: Vec<_> = cache.iter().map(|(k, _)| *k).collect();
+        assert_eq!(order, ["c", "b", "a"]);
+
+        // Hand clears a's bit and takes b; next sweep takes c, then a.
+        cache.put("d", 4);
+        cache.put("e", 5);
+        cache.put("f", 6);
+        assert_eq!(*evicted.lock().unwrap(), ["b", "c", "a"]);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn memory_estimate_tracks_node_storage() {
+        let mut cache = LRUCache::new(64);
+        let empty = cache.memory_estimate();
+        for i in 0..64u32 {
+            cache.put(i, vec![0u8; 100]);
+        }
+        let full = cache.memory_estimate();
+        assert!(full > empty);
+        assert_eq!(cache.memory_estimate_with(|v| v.capacity()), full + 6400);
+
+        for i in 0..60 {
+            cache.remove(&i);
+        }
+        cache.shrink_to_fit();
+        assert!(cache.memory_estimate() < full);
+    }
+
+    #[test]
+    fn builder_combines_options() {
+        let mut cache: LRUCache<&str, i32> = LRUCache::builder()
+            .capacity(2)
+            .policy(EvictionPolicy::Clock)
+            .ttl(Duration::ZERO)
+            .on_evict(|_, _| {})
+            .build();
+        assert_eq!(cache.capacity(), 2);
+
+        cache.put("a", 1);
+        assert_eq!(cache.get(&"a"), None);
+        cache.put_with_ttl("b", 2, Duration::from_secs(3600));
+        assert_eq!(cache.get(&"b"), Some(&2));
+
+        assert_eq!(LRUCache::<u8, u8>::default().capacity(), DEFAULT_CAPACITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "cache capacity must be > 0")]
+    fn builder_rejects_zero_capacity() {
+        LRUCache::<u8, u8>::builder().capacity(0).build();
+    }
+
+    #[test]
+    fn get_or_try_insert_with_failure_leaves_cache_untouched() {
+        let mut cache = LRUCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.remove(&"b");
+        cache.put("b", 2);
+        let order: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+        let free = cache.list.free_slots().to_vec();
+
+        let err = cache.get_or_try_insert_with("c", || Err::<i32, _>("io"));
+        assert_eq!(err, Err("io"));
+        let after: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(after, order);
+        assert_eq!(cache.list.free_slots(), free);
+        assert_eq!(cache.len(), 2);
+
+        let hit = cache.get_or_try_insert_with("a", || Err::<i32, _>("unused"));
+        assert_eq!(hit, Ok(&1));
+        let miss = cache.get_or_try_insert_with("c", || Ok::<_, &str>(3));
+        assert_eq!(miss, Ok(&3));
+        assert!(!cache.contains(&"b"));
+        assert_eq!(*cache.get_or_insert_with("c", || 99), 3);
+    }
+
+    #[test]
+    fn debug_order_follows_accesses_and_links_stay_valid() {
+        let mut cache = LRUCache::new(4);
+        for (i, k) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.put(k, i);
+        }
+        cache.get(&"a");
+        cache.get(&"c");
+        assert_eq!(cache.debug_order(), ["c", "a", "d", "b"]);
+        assert!(cache.validate_links());
+
+        cache.put("e", 4);
+        cache.remove(&"a");
+        cache.get(&"d");
+        assert_eq!(cache.debug_order(), ["d", "e", "c"]);
+        assert!(cache.validate_links());
+    }
+
+    #[test]
+    fn put_clears_previous_ttl() {
+        let mut cache = LRUCache::new(2);
+        cache.put_with_ttl("a", 1, Duration::ZERO);
+        cache.put("a", 2);
+        assert_eq!(cache.get(&"a"), Some(&2));
+    }
+
+    #[test]
+    fn get_or_insert_keeps_existing_value() {
+        let mut cache = LRUCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+
+        assert_eq!(*cache.get_or_insert("a", 10), 1);
+        assert_eq!(cache.debug_order(), ["a", "b"]);
+        assert_eq!(*cache.get_or_insert("c", 3), 3);
+        assert_eq!(cache.debug_order(), ["c", "a"]);
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn iter_mut_updates_values_without_reordering() {
+        let mut cache = LRUCache::new(4);
+        for (i, k) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.put(k, i);
+        }
+        cache.get(&"b");
+        cache.remove(&"c");
+        let order = cache.debug_order();
+
+        for (_, v) in cache.iter_mut() {
+            *v *= 10;
+        }
+        assert_eq!(cache.debug_order(), order);
+        let values: Vec<_> = cache.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(values, [("b", 10), ("d", 30), ("a", 0)]);
+        assert!(cache.validate_links());
+    }
+
+    #[test]
+    fn clock_update_that_grows_weight_keeps_the_entry() {
+        let mut cache: LRUCache<&str, usize> = LRUCache::builder()
+            .capacity(10)
+            .policy(EvictionPolicy::Clock)
+            .weigher(|_, v| *v)
+            .build();
+        for k in ["a", "b", "c"] {
+            cache.put(k, 3);
+        }
+        for k in ["a", "b", "c"] {
+            cache.get(&k);
+        }
+
+        cache.put("a", 6);
+        assert_eq!(cache.get(&"a"), Some(&6));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.current_weight(), 9);
+        assert!(cache.validate_links());
+    }
+
+    #[test]
+    fn get_or_insert_with_refreshes_expired_entry_under_clock() {
+        let mut cache: LRUCache<&str, usize> = LRUCache::builder()
+            .capacity(10)
+            .policy(EvictionPolicy::Clock)
+            .weigher(|_, v| *v)
+            .build();
+        cache.put_with_ttl("a", 3, Duration::ZERO);
+        cache.put("b", 3);
+        cache.put("c", 3);
+        cache.get(&"b");
+        cache.get(&"c");
+
+        assert_eq!(*cache.get_or_insert_with("a", || 6), 6);
+        assert_eq!(*cache.get_or_insert("a", 1), 6);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.validate_links());
+    }
+
+    #[test]
+    fn callbacks_need_not_be_send() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&evicted);
+        let scale = Rc::new(2);
+        let mut cache = LRUCache::builder()
+            .capacity(4)
+            .weigher(move |_, v: &usize| *v * *scale)
+            .on_evict(move |k, _| sink.borrow_mut().push(k))
+            .build();
+        cache.put("a", 1);
+        cache.put("b", 1);
+        cache.put("c", 1);
+        assert_eq!(*evicted.borrow(), ["a"]);
+    }
+}
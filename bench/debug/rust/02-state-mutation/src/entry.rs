@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 /// A single entry in the doubly linked list backing the LRU cache.
 ///
 /// Uses `Option<usize>` indices into the node vector rather than raw
@@ -8,6 +10,14 @@ pub struct CacheEntry<K, V> {
     pub value: V,
     pub prev: Option<usize>,
     pub next: Option<usize>,
+    /// Deadline after which the entry is treated as a miss.  `None`
+    /// means the entry never expires.
+    pub expires_at: Option<Instant>,
+    /// Weight counted against the cache capacity (1 unless the cache
+    /// was built with a weigher).
+    pub weight: usize,
+    /// CLOCK reference bit: set on access, cleared by the sweep hand.
+    pub referenced: bool,
 }
 
 impl<K, V> CacheEntry<K, V> {
@@ -17,6 +27,14 @@ impl<K, V> CacheEntry<K, V> {
             value,
             prev: None,
             next: None,
+            expires_at: None,
+            weight: 1,
+            referenced: false,
         }
     }
+
+    /// Whether the entry's deadline has passed as of `now`.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|deadline| now >= deadline)
+    }
 }
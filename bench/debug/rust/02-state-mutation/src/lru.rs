@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use crate::entry::CacheEntry;
 
 /// An index-based doubly linked list.
@@ -13,7 +15,7 @@ pub struct DoublyLinkedList<K: Clone, V: Clone> {
     len: usize,
 }
 
-impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K, V> {
+impl<K: Clone, V: Clone> DoublyLinkedList<K, V> {
     pub fn new() -> Self {
         DoublyLinkedList {
             nodes: Vec::new(),
@@ -29,6 +31,97 @@ impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K,
         self.len
     }
 
+    /// Remove every entry while keeping the node storage allocated.
+    ///
+    /// All slots are pushed onto the free-list so refilling reuses them.
+    pub fn clear(&mut self) {
+        for slot in self.nodes.iter_mut() {
+            *slot = None;
+        }
+        self.free.clear();
+        self.free.extend((0..self.nodes.len()).rev());
+        self.head = None;
+        self.tail = None;
+        self.len = 0;
+    }
+
+    /// Compact node storage down to the live entries.
+    ///
+    /// Entries are renumbered in list order (head becomes index 0), all
+    /// `prev`/`next` links are rewritten, and the free-list is dropped.
+    /// Any index held by the caller is invalidated.
+    pub fn shrink_to_fit(&mut self) {
+        let mut compacted: Vec<Option<CacheEntry<K, V>>> = Vec::with_capacity(self.len);
+        let mut cur = self.head;
+        while let Some(idx) = cur {
+            let Some(mut node) = self.nodes[idx].take() else {
+                break;
+            };
+            cur = node.next;
+
+            let new_idx = compacted.len();
+            node.prev = new_idx.checked_sub(1);
+            node.next = None;
+            if let Some(Some(prev)) = compacted.last_mut() {
+                prev.next = Some(new_idx);
+            }
+            compacted.push(Some(node));
+        }
+
+        self.head = if compacted.is_empty() { None } else { Some(0) };
+        self.tail = compacted.len().checked_sub(1);
+        self.nodes = compacted;
+        self.free = Vec::new();
+    }
+
+    /// Slot indices currently on the free-list.
+    #[cfg(test)]
+    pub fn free_slots(&self) -> &[usize] {
+        &self.free
+    }
+
+    /// Check that the `next`/`prev` links form one consistent chain.
+    ///
+    /// Verifies that head and tail are the chain's ends, that every
+    /// node's `next` points back via `prev`, and that the walk visits
+    /// exactly `len` nodes (which also rules out cycles).
+    #[cfg(any(test, feature = "debug-internals"))]
+    #[allow(dead_code)]
+    pub fn validate_links(&self) -> bool {
+        let node = |idx: usize| self.nodes.get(idx).and_then(|n| n.as_ref());
+
+        if let Some(h) = self.head {
+            if node(h).is_none_or(|n| n.prev.is_some()) {
+                return false;
+            }
+        }
+
+        let mut count = 0;
+        let mut prev = None;
+        let mut cur = self.head;
+        while let Some(idx) = cur {
+            let Some(n) = node(idx) else {
+                return false;
+            };
+            if n.prev != prev || count == self.len {
+                return false;
+            }
+            count += 1;
+            prev = Some(idx);
+            cur = n.next;
+        }
+        count == self.len && prev == self.tail
+    }
+
+    /// Bytes allocated for node slots and the free-list.
+    ///
+    /// Counts capacity rather than length, so it reflects slots kept for
+    /// reuse.  Heap data owned by keys and values is not included.
+    pub fn heap_bytes(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<Option<CacheEntry<K, V>>>()
+            + self.free.capacity() * std::mem::size_of::<usize>()
+    }
+
     /// Allocate a slot for a new entry (reuse a free slot or grow).
     fn alloc_slot(&mut self, entry: CacheEntry<K, V>) -> usize {
         if let Some(idx) = self.free.pop() {
@@ -99,18 +192,25 @@ impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K,
             node.prev = None;
             node.next = self.head;
         }
+        if let Some(old_head) = self.head {
+            if let Some(ref mut hn) = self.nodes[old_head] {
+                hn.prev = Some(idx);
+            }
+        }
 
         self.head = Some(idx);
     }
 
-    /// Remove and return the key of the tail (least-recently-used) entry.
-    pub fn remove_tail(&mut self) -> Option<K> {
+    /// Remove and return the tail (least-recently-used) entry.
+    pub fn pop_tail(&mut self) -> Option<CacheEntry<K, V>> {
         let tail_idx = self.tail?;
         self.remove_node(tail_idx)
     }
 
-    /// Remove a node by index and return its key.
-    fn remove_node(&mut self, idx: usize) -> Option<K> {
+    /// Remove a node by index and return the detached entry.
+    ///
+    /// The slot is pushed onto the free-list for reuse.
+    pub fn remove_node(&mut self, idx: usize) -> Option<CacheEntry<K, V>> {
         let node = self.nodes[idx].take()?;
         let prev = node.prev;
         let next = node.next;
@@ -133,7 +233,7 @@ impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K,
 
         self.free.push(idx);
         self.len -= 1;
-        Some(node.key)
+        Some(node)
     }
 
     /// Update the value stored at `idx` without moving it.
@@ -143,11 +243,147 @@ impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K,
         }
     }
 
+    /// Weight of the entry at `idx` (0 for a free slot).
+    pub fn weight(&self, idx: usize) -> usize {
+        self.nodes[idx].as_ref().map_or(0, |n| n.weight)
+    }
+
+    /// Set the cached weight of the entry at `idx`.
+    pub fn set_weight(&mut self, idx: usize, weight: usize) {
+        if let Some(ref mut node) = self.nodes[idx] {
+            node.weight = weight;
+        }
+    }
+
+    /// Set the CLOCK reference bit of the entry at `idx`.
+    pub fn set_referenced(&mut self, idx: usize) {
+        if let Some(ref mut node) = self.nodes[idx] {
+            node.referenced = true;
+        }
+    }
+
+    /// Second-chance sweep over the node slots.
+    ///
+    /// Starting at `*hand`, clears the reference bit of each live entry
+    /// until one is found that was already clear, and returns its index.
+    /// `*hand` is left just past the victim.  Free slots and the live
+    /// entry at `skip` are passed over, so the sweep visits the other
+    /// entries in slot order, not list order.
+    pub fn clock_victim(&mut self, hand: &mut usize, skip: Option<usize>) -> Option<usize> {
+        if self.len <= usize::from(skip.is_some()) {
+            return None;
+        }
+        let n = self.nodes.len();
+        loop {
+            let idx = *hand % n;
+            *hand = (idx + 1) % n;
+            if skip == Some(idx) {
+                continue;
+            }
+            if let Some(ref mut node) = self.nodes[idx] {
+                if !node.referenced {
+                    return Some(idx);
+                }
+                node.referenced = false;
+            }
+        }
+    }
+
+    /// Set (or clear) the expiration deadline of the entry at `idx`.
+    pub fn set_expires_at(&mut self, idx: usize, expires_at: Option<Instant>) {
+        if let Some(ref mut node) = self.nodes[idx] {
+            node.expires_at = expires_at;
+        }
+    }
+
+    /// Whether the entry at `idx` has expired as of `now`.
+    pub fn is_expired(&self, idx: usize, now: Instant) -> bool {
+        self.nodes[idx].as_ref().is_some_and(|n| n.is_expired(now))
+    }
+
+    /// Collect the indices of all entries that have expired as of `now`,
+    /// walking from head to tail.
+    pub fn expired_indices(&self, now: Instant) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut cur = self.head;
+        while let Some(idx) = cur {
+            if let Some(ref node) = self.nodes[idx] {
+                if node.is_expired(now) {
+                    result.push(idx);
+                }
+                cur = node.next;
+            } else {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Read the key at `idx`.
+    #[cfg(any(test, feature = "debug-internals"))]
+    pub fn get_key(&self, idx: usize) -> Option<&K> {
+        self.nodes.get(idx)?.as_ref().map(|n| &n.key)
+    }
+
     /// Read the value at `idx`.
     pub fn get_value(&self, idx: usize) -> Option<&V> {
         self.nodes[idx].as_ref().map(|n| &n.value)
     }
 
+    /// Borrow the key and value of the tail (least-recently-used) entry.
+    pub fn peek_tail(&self) -> Option<(&K, &V)> {
+        let node = self.nodes[self.tail?].as_ref()?;
+        Some((&node.key, &node.value))
+    }
+
+    /// Mutably borrow the value at `idx`.
+    pub fn get_value_mut(&mut self, idx: usize) -> Option<&mut V> {
+        self.nodes[idx].as_mut().map(|n| &mut n.value)
+    }
+
+    /// Borrowing iterator from head to tail (most-recently-used first).
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            list: self,
+            cur: self.head,
+            forward: true,
+        }
+    }
+
+    /// Mutably borrowing iterator from head to tail.
+    ///
+    /// The list order is collected up front; each slot's entry is then
+    /// borrowed once, so no two yielded values alias.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        let order: Vec<usize> = {
+            let mut order = Vec::with_capacity(self.len);
+            let mut cur = self.head;
+            while let Some(idx) = cur {
+                let Some(ref node) = self.nodes[idx] else {
+                    break;
+                };
+                order.push(idx);
+                cur = node.next;
+            }
+            order
+        };
+        let mut slots: Vec<Option<&mut CacheEntry<K, V>>> =
+            self.nodes.iter_mut().map(Option::as_mut).collect();
+        order
+            .into_iter()
+            .filter_map(move |idx| slots[idx].take())
+            .map(|node| (&node.key, &mut node.value))
+    }
+
+    /// Borrowing iterator from tail to head (least-recently-used first).
+    pub fn iter_rev(&self) -> Iter<'_, K, V> {
+        Iter {
+            list: self,
+            cur: self.tail,
+            forward: false,
+        }
+    }
+
     /// Iterate from head to tail (most-recently-used first) for debugging.
     #[allow(dead_code)]
     pub fn iter_forward(&self) -> Vec<(K, V)> {
@@ -163,4 +399,99 @@ impl<K: Clone + std::fmt::Debug, V: Clone + std::fmt::Debug> DoublyLinkedList<K,
         }
         result
     }
+
+    /// Iterate from tail to head (least-recently-used first) for debugging.
+    ///
+    /// Follows `prev` links, so it only mirrors `iter_forward` when the
+    /// backward chain is intact.
+    #[allow(dead_code)]
+    pub fn iter_backward(&self) -> Vec<(K, V)> {
+        self.iter_rev()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+}
+
+/// Iterator over list entries, following `next` or `prev` links.
+pub struct Iter<'a, K: Clone, V: Clone> {
+    list: &'a DoublyLinkedList<K, V>,
+    cur: Option<usize>,
+    forward: bool,
+}
+
+impl<'a, K: Clone, V: Clone> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.list.nodes[self.cur?].as_ref()?;
+        self.cur = if self.forward { node.next } else { node.prev };
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrink_to_fit_reindexes_in_list_order() {
+        let mut list: DoublyLinkedList<u32, u32> = DoublyLinkedList::new();
+        let idx: Vec<usize> = (0..6).map(|i| list.push_front(i, i * 10)).collect();
+        list.remove_node(idx[1]);
+        list.remove_node(idx[4]);
+        list.move_to_front(idx[0]);
+
+        let before: Vec<_> = list.iter_forward();
+        list.shrink_to_fit();
+
+        assert_eq!(list.nodes.len(), 4);
+        assert!(list.free.is_empty());
+        assert_eq!(list.iter_forward(), before);
+        let rev: Vec<_> = list.iter_rev().map(|(k, _)| *k).collect();
+        assert_eq!(rev, [2, 3, 5, 0]);
+    }
+
+    #[test]
+    fn backward_walk_mirrors_forward_after_move_to_front() {
+        let mut list: DoublyLinkedList<char, u32> = DoublyLinkedList::new();
+        let idx: Vec<usize> = ['a', 'b', 'c', 'd']
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| list.push_front(k, i as u32))
+            .collect();
+
+        for &i in &[idx[0], idx[2], idx[0], idx[3], idx[1]] {
+            list.move_to_front(i);
+        }
+
+        // Bounded walk first: a broken `prev` chain can cycle.
+        let bounded: Vec<char> = list.iter_rev().take(5).map(|(k, _)| *k).collect();
+        assert_eq!(bounded.len(), 4);
+
+        let forward = list.iter_forward();
+        let mut backward = list.iter_backward();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward[0].0, 'b');
+    }
+
+    #[test]
+    fn clear_retains_node_storage() {
+        let mut list: DoublyLinkedList<u32, u32> = DoublyLinkedList::new();
+        for i in 0..8 {
+            list.push_front(i, i);
+        }
+        let cap = list.nodes.capacity();
+
+        list.clear();
+        assert_eq!(list.len(), 0);
+        assert!(list.iter().next().is_none());
+        assert_eq!(list.nodes.capacity(), cap);
+        assert_eq!(list.free.len(), 8);
+
+        for i in 0..8 {
+            assert!(list.push_front(i, i) < 8);
+        }
+        assert_eq!(list.nodes.len(), 8);
+    }
 }
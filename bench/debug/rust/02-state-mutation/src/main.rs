@@ -1,6 +1,7 @@
 mod entry;
 mod lru;
 mod cache;
+mod sync;
 
 use cache::LRUCache;
 
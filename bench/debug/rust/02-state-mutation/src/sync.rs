@@ -0,0 +1,139 @@
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use crate::cache::{Callbacks, LRUCache, LruCacheBuilder};
+
+/// A thread-safe LRU cache: an `LRUCache` behind a `Mutex`.
+///
+/// Every operation takes the lock for its duration.  Because a reference
+/// into the cache cannot outlive the guard, `get` returns a clone of the
+/// value, so reads require `V: Clone` (already implied by `LRUCache`).
+///
+/// A plain `LRUCache` accepts callbacks that are not `Send`.  The inner
+/// cache here stores [`SendCallbacks`] instead, so the wrapper is `Send`
+/// and `Sync` whenever `K` and `V` are `Send`.
+#[allow(dead_code)]
+pub struct SyncLruCache<K: Clone + Eq + Hash, V: Clone> {
+    inner: Mutex<LRUCache<K, V, SendCallbacks>>,
+}
+
+/// Weigher and eviction callback types that must be `Send`.
+pub struct SendCallbacks;
+
+impl<K, V> Callbacks<K, V> for SendCallbacks {
+    type Weigher = dyn Fn(&K, &V) -> usize + Send;
+    type OnEvict = dyn FnMut(K, V) + Send;
+}
+
+#[allow(dead_code)]
+impl<K: Clone + Eq + Hash, V: Clone> SyncLruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        SyncLruCache {
+            inner: Mutex::new(LruCacheBuilder::default().capacity(capacity).build()),
+        }
+    }
+
+    /// Create a cache bounded by the total weight of its entries.  See
+    /// [`LRUCache::with_weigher`].
+    pub fn with_weigher(
+        max_weight: usize,
+        weigher: impl Fn(&K, &V) -> usize + Send + 'static,
+    ) -> Self {
+        let cache = LruCacheBuilder::<K, V, SendCallbacks>::default()
+            .capacity(max_weight)
+            .boxed_weigher(Box::new(weigher))
+            .build();
+        SyncLruCache {
+            inner: Mutex::new(cache),
+        }
+    }
+
+    /// Register an eviction callback.  See [`LRUCache::on_evict`].
+    pub fn on_evict(self, f: impl FnMut(K, V) + Send + 'static) -> Self {
+        let cache = self.inner.into_inner().unwrap_or_else(|e| e.into_inner());
+        SyncLruCache {
+            inner: Mutex::new(cache.boxed_on_evict(Box::new(f))),
+        }
+    }
+
+    /// Retrieve a clone of the value for `key`, marking it as
+    /// most-recently-used.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.lock().get(key).cloned()
+    }
+
+    /// Insert or update a key-value pair.
+    pub fn put(&self, key: K, value: V) {
+        self.lock().put(key, value);
+    }
+
+    /// Remove `key` and return its value.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lock().remove(key)
+    }
+
+    /// Current number of entries.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Acquire the lock.  A panic while holding it cannot leave the
+    /// cache half-updated in a way later calls would trip over, so a
+    /// poisoned lock is recovered rather than propagated.
+    fn lock(&self) -> std::sync::MutexGuard<'_, LRUCache<K, V, SendCallbacks>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn shared_across_threads() {
+        let cache = Arc::new(SyncLruCache::new(64));
+
+        let handles: Vec<_> = (0..4u32)
+            .map(|t| {
+                let cache = Arc::clone(&cache);
+                thread::spawn(move || {
+                    for i in 0..16 {
+                        cache.put(t * 16 + i, i);
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), 64);
+        assert_eq!(cache.get(&17), Some(1));
+        assert_eq!(cache.remove(&17), Some(1));
+        assert_eq!(cache.get(&17), None);
+    }
+
+    #[test]
+    fn send_callbacks_run_across_threads() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&evicted);
+        let cache = Arc::new(
+            SyncLruCache::with_weigher(4, |_: &u32, v: &u32| *v as usize)
+                .on_evict(move |k, _| sink.lock().unwrap().push(k)),
+        );
+
+        let writer = Arc::clone(&cache);
+        thread::spawn(move || {
+            writer.put(1, 2);
+            writer.put(2, 2);
+            writer.put(3, 2);
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(*evicted.lock().unwrap(), [1]);
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -1,57 +1,718 @@
+use std::borrow::Cow;
+
+use crate::parser::{Format, ParseError};
 use crate::processor::ParsedData;
 
 /// Parse CSV content (comma-separated values with a header row).
 ///
-/// The first non-empty line is treated as the header.  Subsequent
-/// lines are data rows.  Each field is parsed by splitting on commas
-/// and trimming whitespace.
+/// Shorthand for `parse_delimited(content, ',')`.
+pub fn parse_csv(content: &str) -> Result<ParsedData, ParseError> {
+    parse_delimited(content, ',')
+}
+
+/// Parse delimiter-separated values with a header row, e.g. `'\t'` for
+/// TSV or `';'` for semicolon-separated files.
+///
+/// The first non-empty record is treated as the header.  Subsequent
+/// records are data rows.  Fields follow RFC 4180 quoting: a field wrapped
+/// in double quotes may contain the delimiter and line breaks, and `""`
+/// inside it stands for a literal `"`.  Unquoted fields are trimmed of
+/// whitespace (other than the delimiter); quoted fields are kept verbatim.
 ///
-/// # Panics
+/// Returns `Err` if `delim` is not an ASCII character other than `"` and
+/// line breaks, the content is empty, a quoted field is malformed, or any
+/// data row has a different number of fields than the header.  Errors
+/// carry the line (and, within a record, the column) they refer to.
+pub fn parse_delimited(content: &str, delim: char) -> Result<ParsedData, ParseError> {
+    parse_delimited_with(content, delim, CsvOptions::default()).map(|(table, _)| table)
+}
+
+/// A CSV field converted to its column's inferred type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// An empty field, in a column of any type.
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+/// The type inferred for a CSV column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellType {
+    Integer,
+    Float,
+    Boolean,
+    String,
+}
+
+/// A CSV table with typed cells; see `parse_csv_typed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedTable {
+    pub headers: Vec<String>,
+    /// The inferred type of each column, in column order.
+    pub column_types: Vec<CellType>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+/// Parse CSV like `parse_csv`, then infer a type for each column and
+/// convert its fields.
 ///
-/// Panics (via `.unwrap()`) if any data row has a different number of
-/// fields than the header.
-pub fn parse_csv(content: &str) -> Result<ParsedData, String> {
-    let lines: Vec<&str> = content
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
+/// A column's type is the narrowest that fits every non-empty field:
+/// integer (`i64`), then float, otherwise boolean (`true`/`false`, any
+/// case), otherwise string.  Numbers may group digits with underscores,
+/// as in Rust literals (`1_000_000`); an underscore must sit between two
+/// digits.  Empty fields become `Cell::Null` and don't affect the
+/// column's type; a column with no non-empty fields is typed as string.
+#[allow(dead_code)]
+pub fn parse_csv_typed(content: &str) -> Result<TypedTable, ParseError> {
+    let ParsedData::CsvTable { headers, rows } = parse_csv(content)? else {
+        unreachable!("parse_csv always returns a table");
+    };
+
+    let column_types: Vec<CellType> = (0..headers.len())
+        .map(|col| infer_type(rows.iter().map(|row| row[col].as_str())))
+        .collect();
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .zip(&column_types)
+                .map(|(field, &cell_type)| to_cell(field, cell_type))
+                .collect()
+        })
         .collect();
 
-    if lines.is_empty() {
-        return Err("Empty CSV content".into());
+    Ok(TypedTable {
+        headers,
+        column_types,
+        rows,
+    })
+}
+
+fn infer_type<'a>(fields: impl Iterator<Item = &'a str>) -> CellType {
+    let mut fields = fields.filter(|f| !f.is_empty()).peekable();
+    if fields.peek().is_none() {
+        return CellType::String;
     }
 
-    let headers: Vec<String> = lines[0]
-        .split(',')
-        .map(|h| h.trim().to_string())
-        .collect();
+    let (mut int, mut float, mut boolean) = (true, true, true);
+    for field in fields {
+        int &= parse_int(field).is_some();
+        float &= parse_float(field).is_some();
+        boolean &= parse_bool(field).is_some();
+    }
+
+    if int {
+        CellType::Integer
+    } else if float {
+        CellType::Float
+    } else if boolean {
+        CellType::Boolean
+    } else {
+        CellType::String
+    }
+}
+
+fn to_cell(field: String, cell_type: CellType) -> Cell {
+    if field.is_empty() {
+        return Cell::Null;
+    }
+    // `infer_type` checked every non-empty field parses as `cell_type`.
+    match cell_type {
+        CellType::Integer => Cell::Int(parse_int(&field).expect("inferred integer")),
+        CellType::Float => Cell::Float(parse_float(&field).expect("inferred float")),
+        CellType::Boolean => Cell::Bool(parse_bool(&field).expect("inferred boolean")),
+        CellType::String => Cell::Str(field),
+    }
+}
+
+fn parse_int(field: &str) -> Option<i64> {
+    strip_digit_separators(field)?.parse().ok()
+}
+
+/// A finite decimal number; `parse::<f64>` alone would also take `inf`
+/// and `NaN`, which in a CSV are more likely words.
+fn parse_float(field: &str) -> Option<f64> {
+    if !field.contains(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    strip_digit_separators(field)?
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite())
+}
+
+/// `field` without the underscores that group its digits, or `None` if
+/// an underscore is not between two digits (`_1`, `1_`, `1__0`, `1_.5`).
+fn strip_digit_separators(field: &str) -> Option<Cow<'_, str>> {
+    if !field.contains('_') {
+        return Some(Cow::Borrowed(field));
+    }
+    let bytes = field.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        let between_digits = i > 0
+            && bytes[i - 1].is_ascii_digit()
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+        if b == b'_' && !between_digits {
+            return None;
+        }
+    }
+    Some(Cow::Owned(field.replace('_', "")))
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+    if field.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if field.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// How to read the header and treat data rows whose field count differs
+/// from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvOptions {
+    /// Reject ragged rows with an `Err` (the default).  When `false`,
+    /// short rows are padded with empty fields and long rows truncated to
+    /// the header width, each with a warning.
+    pub strict: bool,
+    /// Whether the first record is a header (the default).  When `false`,
+    /// every record is data and the columns are named `col0`, `col1`, ...
+    /// after the width of the first row.
+    pub has_header: bool,
+}
 
-    let num_cols = headers.len();
-    let mut rows: Vec<Vec<String>> = Vec::new();
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            strict: true,
+            has_header: true,
+        }
+    }
+}
+
+/// Like `parse_delimited`, with the header and ragged rows handled per
+/// `options`.
+///
+/// Also returns the warnings for rows that lenient mode repaired.
+pub fn parse_delimited_with(
+    content: &str,
+    delim: char,
+    options: CsvOptions,
+) -> Result<(ParsedData, Vec<String>), ParseError> {
+    let mut reader = CsvReader::with_options(content, delim, options)?;
+    let rows = reader.by_ref().collect::<Result<Vec<_>, _>>()?;
+    let headers = reader.headers().to_vec();
 
-    for (line_no, &line) in lines[1..].iter().enumerate() {
-        let fields: Vec<String> = line
-            .split(',')
-            .map(|f| f.trim().to_string())
-            .collect();
+    Ok((
+        ParsedData::CsvTable { headers, rows },
+        reader.warnings().to_vec(),
+    ))
+}
+
+/// Streams the data rows of delimited content one at a time, so large
+/// inputs need not be held in memory as a table.
+///
+/// The header is read up front by `new`; each row is then checked against
+/// its width as it is yielded.  Quoting follows the same rules as
+/// `parse_delimited`.
+pub struct CsvReader<'a> {
+    headers: Vec<String>,
+    records: Records<'a>,
+    options: CsvOptions,
+    /// The first data row of header-less content, read up front to size
+    /// the columns.
+    pending: Option<RawRecord<'a>>,
+    /// 1-based record number of the next row, counting any header.
+    row_no: usize,
+    warnings: Vec<String>,
+}
+
+impl<'a> CsvReader<'a> {
+    /// Read the header of `content`.
+    ///
+    /// Returns `Err` for an unsupported delimiter, empty content, or a
+    /// malformed header.
+    #[allow(dead_code)]
+    pub fn new(content: &'a str, delim: char) -> Result<Self, ParseError> {
+        Self::with_options(content, delim, CsvOptions::default())
+    }
+
+    /// Like `new`, with the header and ragged rows handled per `options`.
+    pub fn with_options(
+        content: &'a str,
+        delim: char,
+        options: CsvOptions,
+    ) -> Result<Self, ParseError> {
+        let format = match delim {
+            ',' => Format::Csv,
+            _ => Format::Delimited(delim),
+        };
+        if !delim.is_ascii() || matches!(delim, '"' | '\r' | '\n') {
+            return Err(ParseError::new(
+                format,
+                format!("Unsupported delimiter {:?}", delim),
+            ));
+        }
+        let mut records = Records::new(content, delim as u8, format);
+
+        let first = match records.next() {
+            Some(record) => record?,
+            None => return Err(ParseError::new(format, "Empty CSV content".into())),
+        };
+
+        let (headers, pending, row_no) = if options.has_header {
+            (first.fields, None, 2)
+        } else {
+            let headers = (0..first.fields.len())
+                .map(|i| format!("col{}", i))
+                .collect();
+            (headers, Some(first), 1)
+        };
+
+        Ok(CsvReader {
+            headers,
+            records,
+            options,
+            pending,
+            row_no,
+            warnings: Vec::new(),
+        })
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Warnings for the ragged rows repaired so far in lenient mode.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+impl Iterator for CsvReader<'_> {
+    type Item = Result<Vec<String>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.pending.take() {
+            Some(record) => record,
+            None => match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        let row_no = self.row_no;
+        self.row_no += 1;
 
         // Validate that every row has exactly the right number of columns.
-        let valid = (fields.len() == num_cols)
-            .then_some(())
-            .ok_or_else(|| {
-                format!(
-                    "Row {} has {} fields, expected {} (line: {:?})",
-                    line_no + 2,
-                    fields.len(),
-                    num_cols,
-                    line
-                )
-            });
+        let mut fields = record.fields;
+        if fields.len() != self.headers.len() {
+            let mismatch = format!(
+                "Row {} has {} fields, expected {}",
+                row_no,
+                fields.len(),
+                self.headers.len()
+            );
+            if self.options.strict {
+                let message = format!("{} (line: {:?})", mismatch, record.text);
+                return Some(Err(
+                    ParseError::new(self.records.format, message).at_line(record.line)
+                ));
+            }
 
-        valid.unwrap();
+            let repair = if fields.len() < self.headers.len() {
+                "padded with empty fields"
+            } else {
+                "truncated"
+            };
+            self.warnings.push(format!(
+                "{}; {} (line: {:?})",
+                mismatch, repair, record.text
+            ));
+            fields.resize(self.headers.len(), String::new());
+        }
 
-        rows.push(fields);
+        Some(Ok(fields))
     }
+}
 
-    Ok(ParsedData::CsvTable { headers, rows })
+/// One record split out of CSV content.
+struct RawRecord<'a> {
+    fields: Vec<String>,
+    /// The record's source text (which may span several lines), trimmed.
+    text: &'a str,
+    /// 1-based line the record starts on.
+    line: usize,
+}
+
+/// Splits delimited content into records, skipping blank lines.
+///
+/// Works on bytes: the delimiter, quote and line breaks are all ASCII, and
+/// no byte of a multi-byte UTF-8 character can be mistaken for one.
+struct Records<'a> {
+    content: &'a str,
+    delim: u8,
+    /// The format errors are reported as.
+    format: Format,
+    pos: usize,
+    /// 1-based line that `pos` is on, advanced as records are consumed
+    /// so that numbering a record doesn't rescan the content.
+    line: usize,
+}
+
+impl<'a> Records<'a> {
+    fn new(content: &'a str, delim: u8, format: Format) -> Self {
+        Records {
+            content,
+            delim,
+            format,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    /// Read fields up to the end of the record and past its line break.
+    fn read_record(&mut self) -> Result<Vec<String>, ParseError> {
+        let bytes = self.content.as_bytes();
+        let mut fields = Vec::new();
+
+        loop {
+            self.skip_blanks();
+            let field = if bytes.get(self.pos) == Some(&b'"') {
+                let field = self.read_quoted()?;
+                self.skip_blanks();
+                field
+            } else {
+                let start = self.pos;
+                while self.pos < bytes.len()
+                    && bytes[self.pos] != self.delim
+                    && !matches!(bytes[self.pos], b'\r' | b'\n')
+                {
+                    self.pos += 1;
+                }
+                self.content[start..self.pos].trim().to_string()
+            };
+            fields.push(field);
+
+            match bytes.get(self.pos) {
+                Some(&b) if b == self.delim => self.pos += 1,
+                None | Some(b'\r') | Some(b'\n') => {
+                    self.skip_line_break();
+                    return Ok(fields);
+                }
+                Some(_) => {
+                    return Err(self.error_at(self.pos, "Unexpected character after closing quote"));
+                }
+            }
+        }
+    }
+
+    /// Read a quoted field starting at the opening quote, leaving `pos`
+    /// just past the closing quote.
+    fn read_quoted(&mut self) -> Result<String, ParseError> {
+        let opened_at = self.pos;
+        self.pos += 1;
+        let mut field = String::new();
+
+        loop {
+            let rest = &self.content[self.pos..];
+            let Some(quote) = rest.find('"') else {
+                return Err(self.error_at(opened_at, "Unterminated quoted field"));
+            };
+            field.push_str(&rest[..quote]);
+            self.pos += quote + 1;
+
+            // `""` is an escaped quote; anything else closes the field.
+            if self.content.as_bytes().get(self.pos) == Some(&b'"') {
+                field.push('"');
+                self.pos += 1;
+            } else {
+                return Ok(field);
+            }
+        }
+    }
+
+    fn skip_blanks(&mut self) {
+        let bytes = self.content.as_bytes();
+        while self.pos < bytes.len()
+            && bytes[self.pos] != self.delim
+            && matches!(bytes[self.pos], b' ' | b'\t')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_line_break(&mut self) {
+        let bytes = self.content.as_bytes();
+        if bytes.get(self.pos) == Some(&b'\r') {
+            self.pos += 1;
+        }
+        if bytes.get(self.pos) == Some(&b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    /// An error located at byte offset `pos`.
+    fn error_at(&self, pos: usize, message: &str) -> ParseError {
+        ParseError::new(self.format, message.into()).at_offset(self.content, pos)
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<RawRecord<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.content.len() {
+            let start = self.pos;
+            let fields = match self.read_record() {
+                Ok(fields) => fields,
+                Err(e) => {
+                    // Don't keep yielding errors for the same input.
+                    self.pos = self.content.len();
+                    return Some(Err(e));
+                }
+            };
+            let raw = &self.content[start..self.pos];
+            let start_line = self.line;
+            self.line += raw.matches('\n').count();
+            let text = raw.trim();
+            if !text.is_empty() {
+                // Blank lines before the record are part of `raw`.
+                let leading = &raw[..raw.len() - raw.trim_start().len()];
+                let line = start_line + leading.matches('\n').count();
+                return Some(Ok(RawRecord { fields, text, line }));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        match parse_csv(content) {
+            Ok(ParsedData::CsvTable { headers, rows }) => (headers, rows),
+            other => panic!("expected a CSV table, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quoted_field_with_comma() {
+        let (headers, rows) = table("name,hosts\napp, \"alpha, beta\"\n");
+        assert_eq!(headers, ["name", "hosts"]);
+        assert_eq!(rows, [["app", "alpha, beta"]]);
+    }
+
+    #[test]
+    fn escaped_quote() {
+        let (_, rows) = table("id,quote\n1,\"she said \"\"hi\"\"\"\n2,\"\"\"\"\n");
+        assert_eq!(rows, [["1", "she said \"hi\""], ["2", "\""]]);
+    }
+
+    #[test]
+    fn quoted_field_spanning_lines() {
+        let (_, rows) = table("id,note\r\n1,\"first line\r\nsecond, line\"\r\n\r\n2,plain\r\n");
+        assert_eq!(rows, [["1", "first line\r\nsecond, line"], ["2", "plain"]]);
+    }
+
+    #[test]
+    fn tab_and_semicolon_delimiters() {
+        let tsv = "name\thosts\tport\napp\talpha, beta\t8080\nweb\t\t80\n";
+        match parse_delimited(tsv, '\t') {
+            Ok(ParsedData::CsvTable { headers, rows }) => {
+                assert_eq!(headers, ["name", "hosts", "port"]);
+                assert_eq!(rows, [["app", "alpha, beta", "8080"], ["web", "", "80"]]);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+
+        match parse_delimited("a;b\n1,5;\"x;y\"\n", ';') {
+            Ok(ParsedData::CsvTable { rows, .. }) => assert_eq!(rows, [["1,5", "x;y"]]),
+            other => panic!("expected a table, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsupported_delimiters() {
+        for delim in ['"', '\n', '\u{e9}'] {
+            assert!(parse_delimited("a", delim).is_err(), "{:?}", delim);
+        }
+    }
+
+    #[test]
+    fn malformed_quotes_are_errors() {
+        let err = parse_csv("a,b\n1,\"open\n2,3\n").unwrap_err();
+        assert_eq!(err.message, "Unterminated quoted field");
+        assert_eq!((err.line, err.column), (Some(2), Some(3)));
+
+        let err = parse_csv("a,b\n\"x\"y,2\n").unwrap_err();
+        assert_eq!(err.message, "Unexpected character after closing quote");
+        assert_eq!((err.line, err.column), (Some(2), Some(4)));
+    }
+
+    #[test]
+    fn reader_streams_rows() {
+        let mut reader = CsvReader::new("id,name\n1,a\n2\n\n3,\"c\nd\"\n", ',').unwrap();
+        assert_eq!(reader.headers(), ["id", "name"]);
+        assert_eq!(reader.next(), Some(Ok(vec!["1".into(), "a".into()])));
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.message, "Row 3 has 1 fields, expected 2 (line: \"2\")");
+        assert_eq!(err.line, Some(3));
+        assert_eq!(reader.next(), Some(Ok(vec!["3".into(), "c\nd".into()])));
+        assert_eq!(reader.next(), None);
+
+        let err = CsvReader::new(" \n", ',').err().unwrap();
+        assert_eq!(err.message, "Empty CSV content");
+        assert_eq!(err.line, None);
+    }
+
+    #[test]
+    fn ragged_rows_strict_and_lenient() {
+        let short = "a,b,c\n1,2\n";
+        let long = "a,b\n1,2,3\n";
+        let strict = CsvOptions::default();
+        let lenient = CsvOptions {
+            strict: false,
+            ..CsvOptions::default()
+        };
+
+        let err = parse_delimited_with(short, ',', strict).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Row 2 has 2 fields, expected 3 (line: \"1,2\")"
+        );
+        let err = parse_delimited_with(long, ',', strict).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Row 2 has 3 fields, expected 2 (line: \"1,2,3\")"
+        );
+
+        match parse_delimited_with(short, ',', lenient) {
+            Ok((ParsedData::CsvTable { rows, .. }, warnings)) => {
+                assert_eq!(rows, [["1", "2", ""]]);
+                assert_eq!(
+                    warnings,
+                    ["Row 2 has 2 fields, expected 3; padded with empty fields (line: \"1,2\")"]
+                );
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        match parse_delimited_with(long, ',', lenient) {
+            Ok((ParsedData::CsvTable { rows, .. }, warnings)) => {
+                assert_eq!(rows, [["1", "2"]]);
+                assert_eq!(
+                    warnings,
+                    ["Row 2 has 3 fields, expected 2; truncated (line: \"1,2,3\")"]
+                );
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_report_line_of_bad_row() {
+        let content = "id,name\n1,a\n\n2,\"multi\nline\"\n3\n4,d\n";
+        let err = parse_delimited(content, ',').unwrap_err();
+        assert_eq!(err.format, Format::Csv);
+        assert_eq!(err.line, Some(6));
+        assert_eq!(err.column, None);
+
+        let err = parse_delimited("a\tb\n1\n", '\t').unwrap_err();
+        assert_eq!(err.format, Format::Delimited('\t'));
+        assert_eq!(err.line, Some(2));
+    }
+
+    #[test]
+    fn typed_columns() {
+        let content = "id,score,ok,mixed,blank\n1,1.5,true,7,\n,2,FALSE,x,\n3,-4e2,,8,\n";
+        let table = parse_csv_typed(content).unwrap();
+        assert_eq!(
+            table.column_types,
+            [
+                CellType::Integer,
+                CellType::Float,
+                CellType::Boolean,
+                CellType::String,
+                CellType::String
+            ]
+        );
+        assert_eq!(
+            table.rows,
+            [
+                vec![
+                    Cell::Int(1),
+                    Cell::Float(1.5),
+                    Cell::Bool(true),
+                    Cell::Str("7".into()),
+                    Cell::Null
+                ],
+                vec![
+                    Cell::Null,
+                    Cell::Float(2.0),
+                    Cell::Bool(false),
+                    Cell::Str("x".into()),
+                    Cell::Null
+                ],
+                vec![
+                    Cell::Int(3),
+                    Cell::Float(-400.0),
+                    Cell::Null,
+                    Cell::Str("8".into()),
+                    Cell::Null
+                ],
+            ]
+        );
+
+        let table = parse_csv_typed("word\nnan\ninf\n").unwrap();
+        assert_eq!(table.column_types, [CellType::String]);
+    }
+
+    #[test]
+    fn typed_numbers_with_underscores() {
+        let table = parse_csv_typed("n,x\n1_000,2_5.0_1\n-1_0,1e1_0\n").unwrap();
+        assert_eq!(table.column_types, [CellType::Integer, CellType::Float]);
+        assert_eq!(
+            table.rows,
+            [
+                [Cell::Int(1000), Cell::Float(25.01)],
+                [Cell::Int(-10), Cell::Float(1e10)]
+            ]
+        );
+
+        for field in ["_1", "1_", "1__0", "1_.5"] {
+            let table = parse_csv_typed(&format!("n\n{}\n", field)).unwrap();
+            assert_eq!(table.column_types, [CellType::String], "{:?}", field);
+        }
+    }
+
+    #[test]
+    fn headerless_rows_get_synthesized_columns() {
+        let options = CsvOptions {
+            has_header: false,
+            ..CsvOptions::default()
+        };
+        let content = "1,a,x\n2,b,y\n3,c,z\n";
+        match parse_delimited_with(content, ',', options) {
+            Ok((ParsedData::CsvTable { headers, rows }, warnings)) => {
+                assert_eq!(headers, ["col0", "col1", "col2"]);
+                assert_eq!(rows, [["1", "a", "x"], ["2", "b", "y"], ["3", "c", "z"]]);
+                assert!(warnings.is_empty());
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+
+        // The first row's width is what later rows are checked against.
+        let err = parse_delimited_with("1,a,x\n2,b\n", ',', options).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Row 2 has 2 fields, expected 3 (line: \"2,b\")"
+        );
+        assert_eq!(err.line, Some(2));
+    }
 }
@@ -1,93 +1,589 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::processor::ParsedData;
+use crate::parser::{Format, ParseError};
+use crate::processor::{JsonValue, ParsedData};
 
-/// A minimal JSON-array-of-objects parser.
+/// Maximum nesting depth of arrays and objects, so that hostile input
+/// cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Parse a JSON document.
 ///
-/// This only handles the trivial subset used in the benchmark:
+/// The common case used in the benchmark, a flat array of single-level
+/// objects, is returned as `ParsedData::JsonRecords` with every value
+/// rendered as a string:
 ///
 /// ```json
 /// [
-///   {"key": "value", "key2": "value2"},
+///   {"key": "value", "key2": 2},
 ///   ...
 /// ]
 /// ```
 ///
-/// Returns `Err` if the content is not valid simplified-JSON.
-pub fn parse_json(content: &str) -> Result<ParsedData, String> {
-    let trimmed = content.trim();
+/// Any other document (nested objects or arrays, a top-level object or
+/// scalar) is returned as a `ParsedData::JsonValue` tree.
+///
+/// Unlike strict JSON (and `parse_json_serde`), a trailing comma before a
+/// closing `}` or `]` is accepted, as hand-edited fixtures often have one.
+///
+/// Returns `Err`, with the line and column, if the content is not valid
+/// JSON.
+///
+/// This is the dependency-free parser; with the `serde_json` feature,
+/// `parse` uses `parse_json_serde` instead.
+#[cfg_attr(feature = "serde_json", allow(dead_code))]
+pub fn parse_json(content: &str) -> Result<ParsedData, ParseError> {
+    parse_json_value(content).map(into_parsed)
+}
+
+/// Parse a JSON document with `serde_json`, shaped the same way as
+/// `parse_json`.
+#[cfg(feature = "serde_json")]
+pub fn parse_json_serde(content: &str) -> Result<ParsedData, ParseError> {
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|value| into_parsed(from_serde(value)))
+        .map_err(|e| {
+            // serde_json's message ends with the position; keep it structured.
+            let message = e.to_string();
+            let suffix = format!(" at line {} column {}", e.line(), e.column());
+            let message = message
+                .strip_suffix(&suffix)
+                .unwrap_or(&message)
+                .to_string();
+            let err = ParseError::new(Format::Json, message);
+            match e.line() {
+                0 => err,
+                line => ParseError {
+                    column: Some(e.column()),
+                    ..err.at_line(line)
+                },
+            }
+        })
+}
 
-    if !trimmed.starts_with('[') || !trimmed.ends_with(']') {
-        return Err("Not a JSON array".into());
+#[cfg(feature = "serde_json")]
+fn from_serde(value: serde_json::Value) -> JsonValue {
+    match value {
+        serde_json::Value::Null => JsonValue::Null,
+        serde_json::Value::Bool(b) => JsonValue::Bool(b),
+        // Without `arbitrary_precision` every number has an f64 form.
+        serde_json::Value::Number(n) => JsonValue::Number(n.as_f64().unwrap_or(f64::NAN)),
+        serde_json::Value::String(s) => JsonValue::String(s),
+        serde_json::Value::Array(items) => {
+            JsonValue::Array(items.into_iter().map(from_serde).collect())
+        }
+        serde_json::Value::Object(fields) => JsonValue::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, from_serde(value)))
+                .collect(),
+        ),
     }
+}
 
-    let inner = &trimmed[1..trimmed.len() - 1];
-    let mut records: Vec<HashMap<String, String>> = Vec::new();
+/// Parse newline-delimited JSON: one object per non-empty line, collected
+/// into `ParsedData::JsonRecords`.
+///
+/// Scalar values are rendered as `parse_json` renders them; nested arrays
+/// and objects are kept as compact JSON text.  Returns `Err` for the first
+/// line that is not a JSON object, with that line's number.
+pub fn parse_ndjson(content: &str) -> Result<ParsedData, ParseError> {
+    let mut records = Vec::new();
 
-    // Rough split on '},' to get individual objects.
-    for chunk in split_objects(inner) {
-        let chunk = chunk.trim().trim_matches(|c| c == '{' || c == '}');
-        if chunk.is_empty() {
+    for (line_no, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
             continue;
         }
-        let mut map = HashMap::new();
-        for pair in chunk.split(',') {
-            let pair = pair.trim();
-            if pair.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = pair.splitn(2, ':').collect();
-            if parts.len() != 2 {
-                return Err(format!("Invalid JSON pair: {}", pair));
+        let at_line = |err: ParseError| ParseError {
+            format: Format::Ndjson,
+            ..err.at_line(line_no + 1)
+        };
+
+        match parse_json_value(line).map_err(at_line)? {
+            JsonValue::Object(fields) => records.push(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let text = scalar_text(&value).unwrap_or_else(|| value.to_string());
+                        (key, text)
+                    })
+                    .collect(),
+            ),
+            _ => {
+                let err = ParseError::new(Format::Ndjson, "Expected a JSON object".into());
+                return Err(at_line(err));
             }
-            let key = parts[0].trim().trim_matches('"').to_string();
-            let val = parts[1].trim().trim_matches('"').to_string();
-            map.insert(key, val);
-        }
-        if !map.is_empty() {
-            records.push(map);
         }
     }
 
-    if records.is_empty() {
-        return Err("JSON array contained no objects".into());
+    Ok(ParsedData::JsonRecords(records))
+}
+
+/// `JsonRecords` for a flat array of flat objects, otherwise the tree.
+fn into_parsed(value: JsonValue) -> ParsedData {
+    match flat_records(&value) {
+        Some(records) => ParsedData::JsonRecords(records),
+        None => ParsedData::JsonValue(value),
+    }
+}
+
+/// Parse a JSON document into a `JsonValue` tree.
+pub fn parse_json_value(content: &str) -> Result<JsonValue, ParseError> {
+    let mut parser = Parser {
+        src: content,
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < content.len() {
+        return Err(parser.error("Unexpected content after JSON value"));
     }
+    Ok(value)
+}
 
-    Ok(ParsedData::JsonRecords(records))
+/// The records in `value` if it is an array of objects whose values are
+/// all scalars.
+fn flat_records(value: &JsonValue) -> Option<Vec<HashMap<String, String>>> {
+    let JsonValue::Array(items) = value else {
+        return None;
+    };
+    items
+        .iter()
+        .map(|item| match item {
+            JsonValue::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| Some((key.clone(), scalar_text(value)?)))
+                .collect(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A scalar rendered as text, as the flat-records form stores it.
+fn scalar_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => Some("null".into()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
+
+/// Recursive-descent JSON parser over `src`, positioned at byte `pos`.
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    depth: usize,
 }
 
-/// Split the inner content of a JSON array into individual object strings.
-fn split_objects(inner: &str) -> Vec<String> {
-    let mut objects = Vec::new();
-    let mut depth = 0i32;
-    let mut current = String::new();
-
-    for ch in inner.chars() {
-        match ch {
-            '{' => {
-                depth += 1;
-                current.push(ch);
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    /// Consume `byte` if it is next.
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// An error at the current position.
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::new(Format::Json, message.into()).at_offset(self.src, self.pos)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, ParseError> {
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b't') => self.literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.literal("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    /// Count one more level of nesting, failing past `MAX_DEPTH`.
+    fn descend(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("JSON nested too deeply"));
+        }
+        Ok(())
+    }
+
+    fn object(&mut self) -> Result<JsonValue, ParseError> {
+        self.descend()?;
+        self.pos += 1; // '{'
+        let mut fields = BTreeMap::new();
+
+        self.skip_whitespace();
+        if !self.eat(b'}') {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some(b'"') {
+                    return Err(self.error("Expected a string key"));
+                }
+                let key = self.string()?;
+                self.skip_whitespace();
+                if !self.eat(b':') {
+                    return Err(self.error("Expected ':' after object key"));
+                }
+                self.skip_whitespace();
+                let value = self.value()?;
+                fields.insert(key, value);
+
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return Err(self.error("Expected ',' or '}' in object"));
+                }
+                // Tolerate a trailing comma before the closing brace.
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    break;
+                }
             }
-            '}' => {
-                depth -= 1;
-                current.push(ch);
-                if depth == 0 {
-                    objects.push(current.clone());
-                    current.clear();
+        }
+
+        self.depth -= 1;
+        Ok(JsonValue::Object(fields))
+    }
+
+    fn array(&mut self) -> Result<JsonValue, ParseError> {
+        self.descend()?;
+        self.pos += 1; // '['
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if !self.eat(b']') {
+            loop {
+                self.skip_whitespace();
+                items.push(self.value()?);
+
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return Err(self.error("Expected ',' or ']' in array"));
+                }
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    break;
+                }
+            }
+        }
+
+        self.depth -= 1;
+        Ok(JsonValue::Array(items))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let opened_at = self.pos;
+        self.pos += 1; // '"'
+        let mut out = String::new();
+
+        loop {
+            let rest = &self.src[self.pos..];
+            let Some(stop) = rest.find(|c: char| c == '"' || c == '\\' || c < ' ') else {
+                self.pos = opened_at;
+                return Err(self.error("Unterminated string"));
+            };
+            out.push_str(&rest[..stop]);
+            self.pos += stop;
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
                 }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    self.escape(&mut out)?;
+                }
+                _ => return Err(self.error("Control character in string")),
+            }
+        }
+    }
+
+    /// Decode the escape sequence after a backslash into `out`.
+    fn escape(&mut self, out: &mut String) -> Result<(), ParseError> {
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                out.push(self.unicode_escape()?);
+                return Ok(());
             }
-            ',' if depth == 0 => {
-                // skip commas between objects
+            _ => return Err(self.error("Invalid escape sequence")),
+        };
+        self.pos += 1;
+        out.push(ch);
+        Ok(())
+    }
+
+    /// Decode the `XXXX` of a `\uXXXX` escape, combining a UTF-16
+    /// surrogate pair written as two escapes.
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.src[self.pos..].starts_with("\\u") {
+                return Err(self.error("Unpaired surrogate in \\u escape"));
             }
-            _ => {
-                current.push(ch);
+            self.pos += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("Unpaired surrogate in \\u escape"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("Unpaired surrogate in \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + 4)
+            .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Expected four hex digits in \\u escape"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("checked hex digits"))
+    }
+
+    fn number(&mut self) -> Result<JsonValue, ParseError> {
+        let start = self.pos;
+        self.eat(b'-');
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("Invalid number")),
+        }
+        if self.eat(b'.') {
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("Expected digits after decimal point"));
+            }
+            self.digits();
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
             }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(self.error("Expected digits in exponent"));
+            }
+            self.digits();
+        }
+
+        self.src[start..self.pos]
+            .parse()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error("Invalid number"))
+    }
+
+    fn digits(&mut self) {
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, ParseError> {
+        if !self.src[self.pos..].starts_with(word) {
+            return Err(self.error("Unexpected character"));
         }
+        self.pos += word.len();
+        Ok(value)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !current.trim().is_empty() {
-        objects.push(current);
+    fn object(pairs: &[(&str, JsonValue)]) -> JsonValue {
+        JsonValue::Object(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
     }
 
-    objects
+    #[test]
+    fn flat_records_with_comma_in_value() {
+        let content = r#"[{"name": "app", "hosts": "alpha, beta", "port": 8080, "tls": true}]"#;
+        match parse_json(content).unwrap() {
+            ParsedData::JsonRecords(records) => {
+                assert_eq!(records.len(), 1);
+                assert_eq!(records[0]["hosts"], "alpha, beta");
+                assert_eq!(records[0]["port"], "8080");
+                assert_eq!(records[0]["tls"], "true");
+            }
+            other => panic!("expected flat records, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nested_values() {
+        let content = r#"{"a": {"b": 1, "c": [true, null, "x,}"]}, "d": -2.5e1}"#;
+        let expected = object(&[
+            (
+                "a",
+                object(&[
+                    ("b", JsonValue::Number(1.0)),
+                    (
+                        "c",
+                        JsonValue::Array(vec![
+                            JsonValue::Bool(true),
+                            JsonValue::Null,
+                            JsonValue::String("x,}".into()),
+                        ]),
+                    ),
+                ]),
+            ),
+            ("d", JsonValue::Number(-25.0)),
+        ]);
+        assert_eq!(parse_json_value(content).unwrap(), expected);
+
+        match parse_json(r#"[{"a": {"b": 1}}]"#).unwrap() {
+            ParsedData::JsonValue(JsonValue::Array(items)) => assert_eq!(items.len(), 1),
+            other => panic!("expected a value tree, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn string_escapes() {
+        let value = parse_json_value(r#""q\"\\\/\n\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(value, JsonValue::String("q\"\\/\n\u{e9}\u{1F600}".into()));
+    }
+
+    #[test]
+    fn errors_report_position() {
+        let err = parse_json("[\n  {\"a\": 1\n  \"b\": 2}\n]").unwrap_err();
+        assert_eq!(err.message, "Expected ',' or '}' in object");
+        assert_eq!((err.line, err.column), (Some(3), Some(3)));
+
+        let err = parse_json("[\"\u{e9}\u{e9}\", x]").unwrap_err();
+        assert_eq!((err.line, err.column), (Some(1), Some(8)));
+
+        for bad in [
+            "[1,,]",
+            "[,]",
+            "{,}",
+            "{\"a\" 1}",
+            "\"open",
+            "01",
+            "[1] x",
+            "tru",
+            "\"\\ud800\"",
+        ] {
+            assert!(parse_json(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        let err = parse_json_value(&deep).unwrap_err();
+        assert_eq!(err.message, "JSON nested too deeply");
+        assert_eq!(err.column, Some(MAX_DEPTH + 1));
+
+        let ok = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse_json_value(&ok).is_ok());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn serde_matches_hand_rolled() {
+        for content in [
+            r#"[{"name": "app", "hosts": "alpha, beta", "port": 8080}]"#,
+            r#"{"a": {"b": [1, 2.5, null]}, "c": "é\n"}"#,
+        ] {
+            assert_eq!(
+                parse_json(content).unwrap(),
+                parse_json_serde(content).unwrap()
+            );
+        }
+
+        let err = parse_json_serde("[\n1,]").unwrap_err();
+        assert_eq!(err.format, Format::Json);
+        assert_eq!((err.line, err.column), (Some(2), Some(3)));
+        assert!(!err.message.contains("line"), "{}", err.message);
+    }
+
+    #[test]
+    fn ndjson_lines() {
+        let content =
+            "{\"id\": 1, \"msg\": \"a, b\"}\n\n{\"id\": 2, \"tags\": [\"x\", 1]}\r\n{\"id\": 3}\n";
+        match parse_ndjson(content).unwrap() {
+            ParsedData::JsonRecords(records) => {
+                assert_eq!(records.len(), 3);
+                assert_eq!(records[0]["msg"], "a, b");
+                assert_eq!(records[1]["tags"], "[\"x\",1]");
+                assert_eq!(records[2]["id"], "3");
+            }
+            other => panic!("expected records, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ndjson_reports_bad_line() {
+        let err = parse_ndjson("{\"id\": 1}\n{\"id\": 2 \"x\": 3}\n{\"id\": 3}\n").unwrap_err();
+        assert_eq!(err.format, Format::Ndjson);
+        assert_eq!(err.message, "Expected ',' or '}' in object");
+        assert_eq!((err.line, err.column), (Some(2), Some(10)));
+
+        let err = parse_ndjson("{\"id\": 1}\n\n[1]\n").unwrap_err();
+        assert_eq!(err.message, "Expected a JSON object");
+        assert_eq!(err.line, Some(3));
+    }
+
+    #[test]
+    fn trailing_commas_and_multiline_objects() {
+        let pretty = "[\n  {\n    \"name\": \"app\",\n    \"hosts\": \"alpha, beta\",\n  },\n  {\n    \"name\": \"web\"\n  },\n]\n";
+        match parse_json(pretty).unwrap() {
+            ParsedData::JsonRecords(records) => {
+                assert_eq!(records.len(), 2);
+                assert_eq!(records[0]["hosts"], "alpha, beta");
+                assert_eq!(records[0].len(), 2);
+                assert_eq!(records[1]["name"], "web");
+            }
+            other => panic!("expected records, got {:?}", other),
+        }
+
+        assert_eq!(
+            parse_json_value("{\"a\": [1, 2,\n],\n}").unwrap(),
+            object(&[(
+                "a",
+                JsonValue::Array(vec![JsonValue::Number(1.0), JsonValue::Number(2.0)])
+            )])
+        );
+    }
 }
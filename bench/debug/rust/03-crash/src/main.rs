@@ -2,6 +2,7 @@ mod processor;
 mod parser;
 mod json_parser;
 mod csv_parser;
+mod yaml_parser;
 
 use processor::summarise;
 
@@ -18,6 +19,11 @@ timeout = 30
 ";
 
 fn main() {
-    let data = parser::parse(INPUT);
-    summarise(&data);
+    match parser::parse(INPUT) {
+        Ok(data) => summarise(&data),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
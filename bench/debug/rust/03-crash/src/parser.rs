@@ -1,34 +1,215 @@
 use crate::csv_parser;
 use crate::json_parser;
 use crate::processor::ParsedData;
+use crate::yaml_parser;
 
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 /// Supported input formats.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Format {
     Json,
     Csv,
+    /// Like `Csv`, but with the given delimiter instead of a comma.
+    Delimited(char),
     KeyValueConfig,
+    /// Indentation-based `key: value` config; see `parse_yaml_lite`.
+    Yaml,
+    /// Newline-delimited JSON: one object per line.
+    Ndjson,
 }
 
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Json => f.write_str("JSON"),
+            Format::Csv => f.write_str("CSV"),
+            Format::Delimited('\t') => f.write_str("TSV"),
+            Format::Delimited(delim) => write!(f, "{:?}-delimited", delim),
+            Format::KeyValueConfig => f.write_str("key-value config"),
+            Format::Yaml => f.write_str("YAML"),
+            Format::Ndjson => f.write_str("NDJSON"),
+        }
+    }
+}
+
+/// What the key-value config parser does when a key (after `section.`
+/// prefixing) appears more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[allow(dead_code)]
+pub enum DuplicateKeyPolicy {
+    /// Keep the last value, as `parse` always does.
+    #[default]
+    Overwrite,
+    /// Fail with a `ParseError` naming the key and the line it repeats on.
+    Error,
+    /// Keep every value, in file order, in a `ParsedData::MultiConfig`.
+    Collect,
+}
+
+/// Whether the key-value config parser distinguishes `[Network]` from
+/// `[network]` and `Port` from `port`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[allow(dead_code)]
+pub enum CaseMode {
+    /// Keep section and key names as written.
+    #[default]
+    Sensitive,
+    /// Lowercase section and key names, so differently-cased spellings
+    /// share one `section.key`.
+    Insensitive,
+}
+
+/// Looks up the value of a variable for `${VAR}` interpolation.
+pub type VarLookup = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// How the config parser expands `${VAR}` in values.
+#[derive(Clone)]
+pub struct Interpolation {
+    pub lookup: VarLookup,
+    /// Fail on a variable `lookup` doesn't know, instead of leaving its
+    /// `${VAR}` as written.
+    pub error_on_missing: bool,
+}
+
+/// Looks variables up in the process environment and leaves unknown ones
+/// as written.
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation {
+            lookup: Arc::new(|name| std::env::var(name).ok()),
+            error_on_missing: false,
+        }
+    }
+}
+
+/// Options for `parse_config_with`.  The default matches `parse`.
+#[derive(Clone, Default)]
+pub struct ConfigOptions {
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Under `CaseMode::Insensitive`, keys that differ only in case become
+    /// duplicates and are handled by `duplicate_keys`.
+    pub case: CaseMode,
+    /// Expand `${VAR}` in values (off by default).  `$${...}` stands for a
+    /// literal `${...}`.
+    pub interpolate: Option<Interpolation>,
+}
+
+/// Why `parse` could not make sense of its input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The format the content was being parsed as.
+    pub format: Format,
+    /// What the parser for `format` reported.
+    pub message: String,
+    /// 1-based line of the input the error refers to, if known.
+    pub line: Option<usize>,
+    /// 1-based column (in characters) within `line`, if known.
+    pub column: Option<usize>,
+    /// The failed attempt that led `parse` to fall back to `format`, if any.
+    pub previous: Option<Box<ParseError>>,
+}
+
+impl ParseError {
+    pub(crate) fn new(format: Format, message: String) -> Self {
+        ParseError {
+            format,
+            message,
+            line: None,
+            column: None,
+            previous: None,
+        }
+    }
+
+    /// Attach the line the error refers to.
+    pub(crate) fn at_line(self, line: usize) -> Self {
+        ParseError {
+            line: Some(line),
+            ..self
+        }
+    }
+
+    /// Attach the line and column of byte `offset` in `content`.
+    pub(crate) fn at_offset(self, content: &str, offset: usize) -> Self {
+        let before = &content[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        ParseError {
+            column: Some(column),
+            ..self.at_line(line)
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} parse failed", self.format)?;
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ", column {}", column)?;
+            }
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(previous) = &self.previous {
+            write!(f, " (after {})", previous)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 /// Detect the format of `content` by inspecting its first non-blank line.
 ///
+/// A leading UTF-8 byte order mark is ignored.
+///
 /// Heuristics:
+/// - A `[section]` header -> key-value config
 /// - Starts with `[`  -> JSON array
+/// - A whole `{...}` object on the line -> NDJSON
+/// - A `key: value` or `key:` line, with no `[section]` header anywhere
+///   -> YAML
+/// - Contains a tab on the first data line -> TSV (`Delimited('\t')`)
 /// - Contains a comma on the first data line -> CSV
 /// - Otherwise        -> key-value config
 pub fn detect_format(content: &str) -> Format {
+    detect_format_with_delimiter(content, '\t')
+}
+
+/// Like `detect_format`, but checks for `delim` as well as a comma, e.g.
+/// `';'` for semicolon-separated files.  `delim` wins if the first line
+/// contains both.
+pub fn detect_format_with_delimiter(content: &str, delim: char) -> Format {
+    let content = strip_bom(content);
     let first_line = content
         .lines()
         .map(|l| l.trim())
         .find(|l| !l.is_empty())
         .unwrap_or("");
 
+    if is_section_header(first_line) {
+        return Format::KeyValueConfig;
+    }
+
     if first_line.starts_with('[') {
         return Format::Json;
     }
 
+    if first_line.starts_with('{') && first_line.ends_with('}') {
+        return Format::Ndjson;
+    }
+
+    if looks_like_yaml(first_line) && !content.lines().any(|l| is_section_header(l.trim())) {
+        return Format::Yaml;
+    }
+
+    if delim != ',' && first_line.contains(delim) {
+        return Format::Delimited(delim);
+    }
+
     if first_line.contains(',') {
         return Format::Csv;
     }
@@ -36,38 +217,220 @@ pub fn detect_format(content: &str) -> Format {
     Format::KeyValueConfig
 }
 
+/// Whether `line` is a YAML `key: value` pair with a plain identifier key,
+/// rather than an INI `key = value` line or a CSV header.
+fn looks_like_yaml(line: &str) -> bool {
+    if line.contains('=') {
+        return false;
+    }
+    let Some((key, _)) = yaml_parser::split_pair(line) else {
+        return false;
+    };
+    key.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Whether `line` looks like an INI `[section]` header rather than the
+/// start of a JSON array.
+///
+/// The name must start with a letter or `_` and contain only word
+/// characters, whitespace, `.` and `-`, so `[1]`, `[1,2]`, `[{...}]` and
+/// `[true]` are still treated as JSON.
+fn is_section_header(line: &str) -> bool {
+    let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) else {
+        return false;
+    };
+    let name = name.trim();
+
+    let starts_like_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_');
+    let name_chars = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c.is_whitespace() || matches!(c, '_' | '.' | '-'));
+
+    starts_like_name && name_chars && !matches!(name, "true" | "false" | "null")
+}
+
 /// Route content to the appropriate parser.
-pub fn parse(content: &str) -> ParsedData {
-    let format = detect_format(content);
+///
+/// Detects the format with `detect_format` and parses with
+/// `parse_with_format`.  If content detected as JSON fails to parse, CSV
+/// is tried as a guess; when that fails too, the returned error's
+/// `previous` holds the JSON error.
+pub fn parse(content: &str) -> Result<ParsedData, ParseError> {
+    match detect_format(content) {
+        Format::Json => parse_with_format(content, Format::Json).or_else(|json_err| {
+            // JSON parse failed — fall through to CSV as a guess.
+            parse_with_format(content, Format::Csv).map_err(|csv_err| ParseError {
+                previous: Some(Box::new(json_err)),
+                ..csv_err
+            })
+        }),
+        format => parse_with_format(content, format),
+    }
+}
 
+/// Parse content as `format`, skipping detection and any fallback.
+///
+/// A leading UTF-8 byte order mark is ignored.  Lines may end in `\n` or
+/// `\r\n`.
+pub fn parse_with_format(content: &str, format: Format) -> Result<ParsedData, ParseError> {
+    let content = strip_bom(content);
     match format {
-        Format::Json => {
-            match json_parser::parse_json(content) {
-                Ok(data) => data,
-                Err(_) => {
-                    // JSON parse failed — fall through to CSV as a guess.
-                    csv_parser::parse_csv(content).expect("CSV parse also failed")
-                }
-            }
-        }
-        Format::Csv => {
-            csv_parser::parse_csv(content).expect("CSV parse failed")
-        }
+        #[cfg(feature = "serde_json")]
+        Format::Json => json_parser::parse_json_serde(content),
+        #[cfg(not(feature = "serde_json"))]
+        Format::Json => json_parser::parse_json(content),
+        Format::Csv => csv_parser::parse_csv(content),
+        Format::Delimited(delim) => csv_parser::parse_delimited(content, delim),
         Format::KeyValueConfig => {
-            parse_key_value_config(content)
+            parse_key_value_config(content, &ConfigOptions::default())
         }
+        Format::Yaml => yaml_parser::parse_yaml_lite(content),
+        Format::Ndjson => json_parser::parse_ndjson(content),
     }
 }
 
+/// `content` without the UTF-8 byte order mark that Windows tools put at
+/// the start of files, so it doesn't end up in the first key or header.
+fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// Parse content as a key-value config with the given `options`.
+#[allow(dead_code)]
+pub fn parse_config_with(
+    content: &str,
+    options: &ConfigOptions,
+) -> Result<ParsedData, ParseError> {
+    parse_key_value_config(strip_bom(content), options)
+}
+
+/// `value` up to an inline `;` or `#` comment: one at the start or after
+/// whitespace, and not inside single or double quotes.  A marker with no
+/// space before it, as in `http://host/#anchor`, is part of the value.
+/// Inside double quotes, `\"` does not close the quote.
+pub(crate) fn strip_inline_comment(value: &str) -> &str {
+    let mut quote = None;
+    let mut after_space = true;
+    let mut escaped = false;
+
+    for (i, c) in value.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if (c == ';' || c == '#') && after_space => return &value[..i],
+            None => {}
+        }
+        after_space = c.is_whitespace();
+    }
+    value
+}
+
 /// Parse an INI-style key-value configuration file.
 ///
 /// Supports `[section]` headers.  Keys within a section are stored as
-/// `section.key` in the resulting map.
-fn parse_key_value_config(content: &str) -> ParsedData {
-    let mut map = HashMap::new();
+/// `section.key` in the resulting map.  Lines starting with `#` or `;`
+/// are comments, as is the rest of a value from a `#` or `;` that follows
+/// whitespace outside quotes (`port = 8080 ; listening port`).  A value
+/// wrapped in double quotes is unquoted, keeping its surrounding
+/// whitespace and reading `\"`, `\\`, `\n`, `\r` and `\t` escapes.  A key
+/// may be quoted the same way, so it can hold `=` or start with `#`.
+fn parse_key_value_config(
+    content: &str,
+    options: &ConfigOptions,
+) -> Result<ParsedData, ParseError> {
+    let mut entries = config_entries(content, options.case);
+    if let Some(interpolation) = &options.interpolate {
+        for (_, value, line) in &mut entries {
+            *value = interpolate(value, interpolation, *line)?;
+        }
+    }
+
+    match options.duplicate_keys {
+        DuplicateKeyPolicy::Overwrite => Ok(ParsedData::Config(
+            entries.into_iter().map(|(key, value, _)| (key, value)).collect(),
+        )),
+        DuplicateKeyPolicy::Error => {
+            let mut map = HashMap::new();
+            let mut first_lines = HashMap::new();
+            for (key, value, line) in entries {
+                if let Some(first) = first_lines.insert(key.clone(), line) {
+                    let message = format!("Duplicate key {:?} (first set on line {})", key, first);
+                    return Err(ParseError::new(Format::KeyValueConfig, message).at_line(line));
+                }
+                map.insert(key, value);
+            }
+            Ok(ParsedData::Config(map))
+        }
+        DuplicateKeyPolicy::Collect => {
+            let mut map: HashMap<String, Vec<String>> = HashMap::new();
+            for (key, value, _) in entries {
+                map.entry(key).or_default().push(value);
+            }
+            Ok(ParsedData::MultiConfig(map))
+        }
+    }
+}
+
+/// Expand the `${VAR}` references in `value`, found on `line`.
+///
+/// Replacement text is not expanded again, and `$${` is an escaped `${`.
+fn interpolate(
+    value: &str,
+    interpolation: &Interpolation,
+    line: usize,
+) -> Result<String, ParseError> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(dollar) = rest.find('$') {
+        out.push_str(&rest[..dollar]);
+        let tail = &rest[dollar..];
+
+        if let Some(after) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+        let reference = tail
+            .strip_prefix("${")
+            .and_then(|body| body.find('}').map(|end| &body[..end]));
+        let Some(name) = reference else {
+            out.push('$');
+            rest = &tail[1..];
+            continue;
+        };
+
+        match (interpolation.lookup)(name) {
+            Some(expanded) => out.push_str(&expanded),
+            None if interpolation.error_on_missing => {
+                let message = format!("Undefined variable {:?}", name);
+                return Err(ParseError::new(Format::KeyValueConfig, message).at_line(line));
+            }
+            None => out.push_str(&tail[..name.len() + 3]),
+        }
+        rest = &tail[name.len() + 3..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// The `(section.key, value, line)` entries of a key-value config, in file
+/// order.
+fn config_entries(content: &str, case: CaseMode) -> Vec<(String, String, usize)> {
+    let mut entries = Vec::new();
     let mut current_section = String::new();
 
-    for line in content.lines() {
+    for (line_no, line) in content.lines().enumerate() {
         let line = line.trim();
 
         if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
@@ -81,21 +444,73 @@ fn parse_key_value_config(content: &str) -> ParsedData {
         }
 
         // Key = value pair
-        if let Some(eq_pos) = line.find('=') {
-            let key = line[..eq_pos].trim();
-            let value = line[eq_pos + 1..].trim();
+        let pair = split_quoted_key(line).or_else(|| {
+            let eq_pos = line.find('=')?;
+            Some((line[..eq_pos].trim().to_string(), &line[eq_pos + 1..]))
+        });
+        if let Some((key, value)) = pair {
+            let value = strip_inline_comment(value).trim();
+            let value = unquote_value(value).unwrap_or_else(|| value.to_string());
 
             let full_key = if current_section.is_empty() {
                 key.to_string()
             } else {
                 format!("{}.{}", current_section, key)
             };
+            let full_key = match case {
+                CaseMode::Sensitive => full_key,
+                CaseMode::Insensitive => full_key.to_lowercase(),
+            };
 
-            map.insert(full_key, value.to_string());
+            entries.push((full_key, value, line_no + 1));
         }
     }
 
-    ParsedData::Config(map)
+    entries
+}
+
+/// Split a `"key" = value` line after its double-quoted key, returning
+/// the decoded key and the text after the `=`.  `None` if the line does
+/// not start with a quoted key followed by `=`.
+fn split_quoted_key(line: &str) -> Option<(String, &str)> {
+    if !line.starts_with('"') {
+        return None;
+    }
+    let mut escaped = false;
+    let (end, _) = line.char_indices().skip(1).find(|&(_, c)| {
+        let closes = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        closes
+    })?;
+    let key = unquote_value(&line[..=end])?;
+    let value = line[end + 1..].trim_start().strip_prefix('=')?;
+    Some((key, value))
+}
+
+/// The contents of a value that is one double-quoted string, with its
+/// escapes decoded, or `None` for any other value.
+fn unquote_value(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                c @ ('"' | '\\') => out.push(c),
+                c => {
+                    out.push('\\');
+                    out.push(c);
+                }
+            },
+            // An unescaped quote ends the string early: `"a" "b"`.
+            '"' => return None,
+            c => out.push(c),
+        }
+    }
+    Some(out)
 }
 
 #[cfg(test)]
@@ -115,4 +530,348 @@ mod tests {
         );
     }
 
+    #[test]
+    fn detect_tsv() {
+        let tsv = "name\thosts\napp\talpha, beta\n";
+        assert_eq!(detect_format(tsv), Format::Delimited('\t'));
+        match parse(tsv).unwrap() {
+            ParsedData::CsvTable { headers, rows } => {
+                assert_eq!(headers, ["name", "hosts"]);
+                assert_eq!(rows, [["app", "alpha, beta"]]);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detect_hinted_delimiter() {
+        let content = "a;b,c\n1;2,3";
+        assert_eq!(detect_format(content), Format::Csv);
+        assert_eq!(detect_format_with_delimiter(content, ';'), Format::Delimited(';'));
+        assert_eq!(detect_format_with_delimiter("a,b", ';'), Format::Csv);
+        assert_eq!(Format::Delimited(';').to_string(), "';'-delimited");
+    }
+
+    #[test]
+    fn detect_config_bug() {
+        let ini = "[metadata]\nname = test_app\n\n[network]\nallowed_hosts = alpha, beta\n";
+        assert_eq!(detect_format(ini), Format::KeyValueConfig);
+        assert_eq!(detect_format("[server.http]\nport = 80"), Format::KeyValueConfig);
+        assert_eq!(detect_format("  [ my_section ]  \nk = v"), Format::KeyValueConfig);
+
+        match parse(ini).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["metadata.name"], "test_app");
+                assert_eq!(map["network.allowed_hosts"], "alpha, beta");
+            }
+            other => panic!("expected config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bracketed_json_stays_json() {
+        let cases = [
+            "[1,2]",
+            "[1]",
+            "[true]",
+            "[null]",
+            "[\"a\"]",
+            "[{\"a\": 1}]",
+            "[]",
+            "[\n{\"a\": 1}\n]",
+        ];
+        for content in cases {
+            assert_eq!(detect_format(content), Format::Json, "{}", content);
+        }
+    }
+
+    #[test]
+    fn ragged_csv_is_an_error() {
+        let err = parse("a,b\n1,2\n3").unwrap_err();
+        assert_eq!(err.format, Format::Csv);
+        assert!(err.message.contains("Row 3 has 1 fields, expected 2"), "{}", err);
+        assert_eq!(err.previous, None);
+    }
+
+    #[test]
+    fn failed_fallback_keeps_both_errors() {
+        let err = parse("[not json\na,b\nc").unwrap_err();
+        assert_eq!(err.format, Format::Csv);
+        let previous = err.previous.as_deref().expect("JSON error was dropped");
+        assert_eq!(previous.format, Format::Json);
+        assert_eq!(previous.line, Some(1));
+        #[cfg(not(feature = "serde_json"))]
+        assert_eq!(
+            err.to_string(),
+            "CSV parse failed at line 2: Row 2 has 2 fields, expected 1 (line: \"a,b\") \
+             (after JSON parse failed at line 1, column 2: Unexpected character)"
+        );
+        assert!(err.to_string().contains(&format!("(after {})", previous)));
+    }
+
+    #[test]
+    fn explicit_format_skips_detection() {
+        // Detected as JSON (the first line is not a section header), but the
+        // caller knows it is a config file.
+        let content = "[1 server]\nhost = example.com\n";
+        assert_eq!(detect_format(content), Format::Json);
+        match parse_with_format(content, Format::KeyValueConfig) {
+            Ok(ParsedData::Config(map)) => assert_eq!(map["1 server.host"], "example.com"),
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        // No CSV fallback when the format is given.
+        let err = parse_with_format("[not json\na,b\n", Format::Json).unwrap_err();
+        assert_eq!(err.format, Format::Json);
+        assert_eq!(err.previous, None);
+    }
+
+    #[test]
+    fn detect_yaml() {
+        let yaml = "name: test_app\nnetwork:\n  allowed_hosts: alpha, beta\n";
+        assert_eq!(detect_format(yaml), Format::Yaml);
+        match parse(yaml).unwrap() {
+            ParsedData::Config(map) => assert_eq!(map["network.allowed_hosts"], "alpha, beta"),
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        // A `[section]` header anywhere keeps it INI; `=` or a non-identifier
+        // key means it isn't a YAML pair.
+        assert_eq!(detect_format("name: app\n[network]\nport = 1\n"), Format::KeyValueConfig);
+        assert_eq!(detect_format("url = http://x\n"), Format::KeyValueConfig);
+        assert_eq!(detect_format("time: 10:30, place\n"), Format::Yaml);
+        assert_eq!(detect_format("a b: c, d\n"), Format::Csv);
+    }
+
+    #[test]
+    fn bom_and_crlf_are_ignored() {
+        let ini = "\u{feff}[metadata]\r\nname = test_app\r\n\r\n[network]\r\nport = 8080\r\n";
+        assert_eq!(detect_format(ini), Format::KeyValueConfig);
+        match parse(ini).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["metadata.name"], "test_app");
+                assert_eq!(map["network.port"], "8080");
+                assert_eq!(map.len(), 2);
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        match parse("\u{feff}id,name\r\n1,a\r\n").unwrap() {
+            ParsedData::CsvTable { headers, rows } => {
+                assert_eq!(headers, ["id", "name"]);
+                assert_eq!(rows, [["1", "a"]]);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+
+        assert_eq!(detect_format("\u{feff}[{\"a\": 1}]"), Format::Json);
+        assert!(matches!(
+            parse("\u{feff}[{\"a\": 1}]\r\n"),
+            Ok(ParsedData::JsonRecords(_))
+        ));
+
+        match parse("\u{feff}name: app\r\nnet:\r\n  port: 1\r\n").unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["name"], "app");
+                assert_eq!(map["net.port"], "1");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn duplicate_key_policies() {
+        let content = "[net]\nport = 80\nhost = a\n\n[net]\nport = 8080\n";
+
+        let with = |duplicate_keys| ConfigOptions {
+            duplicate_keys,
+            ..ConfigOptions::default()
+        };
+
+        match parse_config_with(content, &with(DuplicateKeyPolicy::Overwrite)).unwrap() {
+            ParsedData::Config(map) => assert_eq!(map["net.port"], "8080"),
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        let err = parse_config_with(content, &with(DuplicateKeyPolicy::Error)).unwrap_err();
+        assert_eq!(err.format, Format::KeyValueConfig);
+        assert_eq!(err.message, "Duplicate key \"net.port\" (first set on line 2)");
+        assert_eq!(err.line, Some(6));
+
+        match parse_config_with(content, &with(DuplicateKeyPolicy::Collect)).unwrap() {
+            ParsedData::MultiConfig(map) => {
+                assert_eq!(map["net.port"], ["80", "8080"]);
+                assert_eq!(map["net.host"], ["a"]);
+            }
+            other => panic!("expected a multi-value config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn detect_ndjson() {
+        let content = "{\"level\": \"info\", \"msg\": \"started\"}\n{\"level\": \"warn\"}\n";
+        assert_eq!(detect_format(content), Format::Ndjson);
+        match parse(content).unwrap() {
+            ParsedData::JsonRecords(records) => assert_eq!(records[1]["level"], "warn"),
+            other => panic!("expected records, got {:?}", other),
+        }
+
+        // An object spread over several lines is not NDJSON.
+        assert_ne!(detect_format("{\n  \"a\": 1\n}\n"), Format::Ndjson);
+    }
+
+    #[test]
+    fn case_insensitive_sections_merge() {
+        let content = "[Network]\nPort = 80\nHost = a\n\n[network]\nport = 8080\n";
+
+        match parse_config_with(content, &ConfigOptions::default()).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["Network.Port"], "80");
+                assert_eq!(map["network.port"], "8080");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        let insensitive = ConfigOptions {
+            case: CaseMode::Insensitive,
+            ..ConfigOptions::default()
+        };
+        match parse_config_with(content, &insensitive).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map.len(), 2);
+                assert_eq!(map["network.port"], "8080");
+                assert_eq!(map["network.host"], "a");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        let strict = ConfigOptions {
+            duplicate_keys: DuplicateKeyPolicy::Error,
+            ..insensitive.clone()
+        };
+        let err = parse_config_with(content, &strict).unwrap_err();
+        assert_eq!(err.message, "Duplicate key \"network.port\" (first set on line 2)");
+    }
+
+    #[test]
+    fn inline_comments() {
+        let content = "\
+# whole-line comment
+; another
+[net]
+port = 8080  ; listening port
+host = example.com # primary
+sep = \"a;b\" ; quoted
+hash = 'x # y'
+url = http://x/#frag
+empty = ; nothing
+";
+        match parse_config_with(content, &ConfigOptions::default()).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["net.port"], "8080");
+                assert_eq!(map["net.host"], "example.com");
+                assert_eq!(map["net.sep"], "a;b");
+                assert_eq!(map["net.hash"], "'x # y'");
+                assert_eq!(map["net.url"], "http://x/#frag");
+                assert_eq!(map["net.empty"], "");
+                assert_eq!(map.len(), 6);
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn variable_interpolation() {
+        let content = "\
+[paths]
+data = ${HOME}/data
+both = ${HOME}:${USER} ($)
+missing = ${NOPE}/x
+escaped = $${HOME} and $$ and ${unclosed
+";
+        let vars = HashMap::from([("HOME", "/home/app"), ("USER", "${HOME}")]);
+        let lookup: VarLookup = Arc::new(move |name| vars.get(name).map(|v| v.to_string()));
+        let lenient = ConfigOptions {
+            interpolate: Some(Interpolation {
+                lookup: lookup.clone(),
+                error_on_missing: false,
+            }),
+            ..ConfigOptions::default()
+        };
+
+        match parse_config_with(content, &lenient).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["paths.data"], "/home/app/data");
+                assert_eq!(map["paths.both"], "/home/app:${HOME} ($)");
+                assert_eq!(map["paths.missing"], "${NOPE}/x");
+                assert_eq!(map["paths.escaped"], "${HOME} and $$ and ${unclosed");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        // Without interpolation, values are kept as written.
+        match parse_config_with(content, &ConfigOptions::default()).unwrap() {
+            ParsedData::Config(map) => assert_eq!(map["paths.data"], "${HOME}/data"),
+            other => panic!("expected a config, got {:?}", other),
+        }
+
+        let strict = ConfigOptions {
+            interpolate: Some(Interpolation {
+                lookup,
+                error_on_missing: true,
+            }),
+            ..ConfigOptions::default()
+        };
+        let err = parse_config_with(content, &strict).unwrap_err();
+        assert_eq!(err.message, "Undefined variable \"NOPE\"");
+        assert_eq!(err.line, Some(4));
+    }
+
+
+    #[test]
+    fn quoted_values() {
+        let content = "\
+plain = \"a b\"
+comment = \"a \\\" ; b\" ; real comment
+padded = \"  x \"
+escaped = \"say \\\"hi\\\"\\n\\tC:\\\\dir \\d\"
+two = \"a\" \"b\"
+open = \"a
+";
+        match parse(content).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["plain"], "a b");
+                assert_eq!(map["comment"], "a \" ; b");
+                assert_eq!(map["padded"], "  x ");
+                assert_eq!(map["escaped"], "say \"hi\"\n\tC:\\dir \\d");
+                assert_eq!(map["two"], "\"a\" \"b\"");
+                assert_eq!(map["open"], "\"a");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quoted_keys() {
+        let content = "\
+\"a=b\" = 1
+\"#k\"=2
+\" sp \" = \"3\"
+\"q\\\"\" = 4
+\"odd\" x = 5
+[net]
+\"[x\" = y]
+";
+        match parse(content).unwrap() {
+            ParsedData::Config(map) => {
+                assert_eq!(map["a=b"], "1");
+                assert_eq!(map["#k"], "2");
+                assert_eq!(map[" sp "], "3");
+                assert_eq!(map["q\""], "4");
+                assert_eq!(map["\"odd\" x"], "5");
+                assert_eq!(map["net.[x"], "y]");
+            }
+            other => panic!("expected a config, got {:?}", other),
+        }
+    }
 }
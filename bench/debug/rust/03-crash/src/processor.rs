@@ -1,12 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::parser::{detect_format, strip_inline_comment, Format};
+
+/// A JSON value tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(BTreeMap<String, JsonValue>),
+}
 
 /// Accepted internal representation produced by any parser.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ParsedData {
     /// A flat key-value config (section headers become `section.key`).
     Config(HashMap<String, String>),
+    /// Like `Config`, keeping every value of a repeated key in file order
+    /// (see `DuplicateKeyPolicy::Collect`).
+    MultiConfig(HashMap<String, Vec<String>>),
     /// A list of JSON-like objects (simplified to key-value maps).
     JsonRecords(Vec<HashMap<String, String>>),
+    /// Any JSON document that isn't a flat list of flat objects.
+    JsonValue(JsonValue),
     /// Tabular CSV data: header row + data rows.
     CsvTable {
         headers: Vec<String>,
@@ -14,15 +33,363 @@ pub enum ParsedData {
     },
 }
 
+impl ParsedData {
+    /// Look up a single value by key.
+    ///
+    /// - `Config`: the value stored under `key` (`section.key` for keys
+    ///   inside a section).
+    /// - `MultiConfig`: the last value stored under `key`.
+    /// - `JsonRecords`: the value of `key` in the first record.
+    /// - `JsonValue`: the member `key` of a top-level object, if it is a
+    ///   string.
+    /// - `CsvTable`: always `None`; use the headers and rows directly.
+    #[allow(dead_code)]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self {
+            ParsedData::Config(map) => map.get(key).map(String::as_str),
+            ParsedData::MultiConfig(map) => map.get(key)?.last().map(String::as_str),
+            ParsedData::JsonRecords(records) => records.first()?.get(key).map(String::as_str),
+            ParsedData::JsonValue(JsonValue::Object(fields)) => match fields.get(key)? {
+                JsonValue::String(s) => Some(s),
+                _ => None,
+            },
+            ParsedData::JsonValue(_) | ParsedData::CsvTable { .. } => None,
+        }
+    }
+
+    /// Look up a comma-separated value as a list, e.g. `["alpha", "beta"]`
+    /// for `allowed_hosts = alpha, beta`.
+    ///
+    /// Items are trimmed and empty items dropped.  Lookup follows `get`,
+    /// except that a `MultiConfig` key yields the items of all its values
+    /// in file order.
+    #[allow(dead_code)]
+    pub fn get_list(&self, key: &str) -> Option<Vec<&str>> {
+        let values: Vec<&str> = match self {
+            ParsedData::MultiConfig(map) => map.get(key)?.iter().map(String::as_str).collect(),
+            _ => vec![self.get(key)?],
+        };
+        Some(
+            values
+                .into_iter()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect(),
+        )
+    }
+
+    /// Export as compact JSON, for storage alongside inputs of other
+    /// formats.  Unlike `to_string`, this is one-way:
+    ///
+    /// - `Config`: an object keyed by `section.key`.
+    /// - `MultiConfig`: the same, with an array of each key's values.
+    /// - `JsonRecords`: an array of objects with string values.
+    /// - `JsonValue`: the value itself.
+    /// - `CsvTable`: an array with one object per row, keyed by header (a
+    ///   repeated header keeps its last column).
+    #[allow(dead_code)]
+    pub fn to_json(&self) -> String {
+        let string = |s: &String| JsonValue::String(s.clone());
+        let object = |pairs: &mut dyn Iterator<Item = (&String, &String)>| {
+            JsonValue::Object(pairs.map(|(k, v)| (k.clone(), string(v))).collect())
+        };
+
+        let value = match self {
+            ParsedData::Config(map) => object(&mut map.iter()),
+            ParsedData::MultiConfig(map) => JsonValue::Object(
+                map.iter()
+                    .map(|(k, values)| {
+                        (
+                            k.clone(),
+                            JsonValue::Array(values.iter().map(string).collect()),
+                        )
+                    })
+                    .collect(),
+            ),
+            ParsedData::JsonRecords(records) => {
+                JsonValue::Array(records.iter().map(|r| object(&mut r.iter())).collect())
+            }
+            ParsedData::JsonValue(value) => value.clone(),
+            ParsedData::CsvTable { headers, rows } => JsonValue::Array(
+                rows.iter()
+                    .map(|row| object(&mut headers.iter().zip(row)))
+                    .collect(),
+            ),
+        };
+        value.to_string()
+    }
+
+    /// The number of entries.
+    ///
+    /// - `Config`, `MultiConfig`: the number of distinct keys.
+    /// - `JsonRecords`: the number of records.
+    /// - `JsonValue`: the length of a top-level array, otherwise 1.
+    /// - `CsvTable`: the number of data rows, excluding the header.
+    pub fn row_count(&self) -> usize {
+        match self {
+            ParsedData::Config(map) => map.len(),
+            ParsedData::MultiConfig(map) => map.len(),
+            ParsedData::JsonRecords(records) => records.len(),
+            ParsedData::JsonValue(JsonValue::Array(items)) => items.len(),
+            ParsedData::JsonValue(_) => 1,
+            ParsedData::CsvTable { rows, .. } => rows.len(),
+        }
+    }
+
+    /// The keys that values can be found under, sorted except for CSV.
+    ///
+    /// - `Config`, `MultiConfig`: every key.
+    /// - `JsonRecords`: the union of all records' keys.
+    /// - `JsonValue`: the members of a top-level object, otherwise none.
+    /// - `CsvTable`: the headers, in column order.
+    #[allow(dead_code)]
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            ParsedData::Config(map) => sorted(map.keys()),
+            ParsedData::MultiConfig(map) => sorted(map.keys()),
+            ParsedData::JsonRecords(records) => {
+                let keys: BTreeSet<&String> = records.iter().flat_map(|r| r.keys()).collect();
+                keys.into_iter().cloned().collect()
+            }
+            ParsedData::JsonValue(JsonValue::Object(fields)) => fields.keys().cloned().collect(),
+            ParsedData::JsonValue(_) => Vec::new(),
+            ParsedData::CsvTable { headers, .. } => headers.clone(),
+        }
+    }
+}
+
+/// Canonical text form, which parses back to an equal `ParsedData`:
+///
+/// - `Config`: INI, keys sorted, with `section.key` entries grouped under
+///   `[section]` headers (split at the first `.`) after the keys that have
+///   no section.  A key whose section is empty (`.x`) or would not survive
+///   a header (surrounding whitespace, a line break) is written whole,
+///   with the keys that have no section.  Keys and values are written
+///   verbatim unless they would not read back unchanged, in which case
+///   they are double-quoted with backslash escapes: a key containing `=`
+///   or starting with `#`, `;`, `[` or `"`, and a value read as an inline
+///   comment, such as `x ; y`, or starting with `"`.  Surrounding
+///   whitespace and line breaks are quoted in both.  If the first line
+///   would be detected as another format, as `hosts = a, b` would be as
+///   CSV, a `; config` comment is written first.
+/// - `MultiConfig`: the same, with one line per value; it parses back with
+///   `DuplicateKeyPolicy::Collect`.
+/// - `CsvTable`: comma-separated, with the header fields always quoted (so
+///   detection cannot take the header for YAML or config) and other fields
+///   quoted when needed.  A single-column table is detected as config, so
+///   parse it back with `parse_with_format`.
+/// - `JsonRecords`: a JSON array of objects with string values.
+/// - `JsonValue`: compact JSON; NaN and infinities are written as `null`.
+impl fmt::Display for ParsedData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsedData::Config(map) => write_config(f, map.iter().collect()),
+            ParsedData::MultiConfig(map) => {
+                let entries = map
+                    .iter()
+                    .flat_map(|(key, values)| values.iter().map(move |value| (key, value)));
+                write_config(f, entries.collect())
+            }
+            ParsedData::JsonRecords(records) => {
+                f.write_str("[")?;
+                for (i, record) in records.iter().enumerate() {
+                    f.write_str(if i == 0 { "\n  {" } else { ",\n  {" })?;
+                    let fields: BTreeMap<_, _> = record.iter().collect();
+                    for (j, (key, value)) in fields.into_iter().enumerate() {
+                        if j > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write_json_string(f, key)?;
+                        f.write_str(": ")?;
+                        write_json_string(f, value)?;
+                    }
+                    f.write_str("}")?;
+                }
+                f.write_str(if records.is_empty() { "]\n" } else { "\n]\n" })
+            }
+            ParsedData::JsonValue(value) => writeln!(f, "{}", value),
+            ParsedData::CsvTable { headers, rows } => {
+                let header: Vec<String> = headers.iter().map(|h| quote_csv(h)).collect();
+                writeln!(f, "{}", header.join(","))?;
+                for row in rows {
+                    let fields: Vec<String> = row
+                        .iter()
+                        .map(|field| {
+                            if needs_quotes(field) {
+                                quote_csv(field)
+                            } else {
+                                field.clone()
+                            }
+                        })
+                        .collect();
+                    writeln!(f, "{}", fields.join(","))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Write `(section.key, value)` entries as INI; see `Display`.
+fn write_config(f: &mut fmt::Formatter<'_>, mut entries: Vec<(&String, &String)>) -> fmt::Result {
+    // A section that would not read back from a `[section]` header stays
+    // part of the key, which is then written before the first header.
+    let split = |key: &str| match key.split_once('.') {
+        Some((section, key)) if is_plain_section(section) => (section.to_string(), key.to_string()),
+        _ => (String::new(), key.to_string()),
+    };
+    // Sort by (section, key), keeping repeated keys in their given order.
+    entries.sort_by_key(|(key, _)| split(key));
+
+    let mut out = String::new();
+    let mut current = String::new();
+    for (i, (full_key, value)) in entries.into_iter().enumerate() {
+        let (section, key) = split(full_key);
+        if section != current {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", section));
+            current = section;
+        }
+        if needs_key_quotes(&key) {
+            out.push_str(&quote_config(&key));
+        } else {
+            out.push_str(&key);
+        }
+        out.push_str(" = ");
+        if needs_config_quotes(value) {
+            out.push_str(&quote_config(value));
+        } else {
+            out.push_str(value);
+        }
+        out.push('\n');
+    }
+
+    if detect_format(&out) != Format::KeyValueConfig {
+        f.write_str("; config\n")?;
+    }
+    f.write_str(&out)
+}
+
+/// Whether `section` reads back unchanged from a `[section]` header.
+fn is_plain_section(section: &str) -> bool {
+    !section.is_empty() && section.trim() == section && !section.contains(['\r', '\n'])
+}
+
+/// Whether a config key must be quoted to parse back unchanged: it would
+/// otherwise be cut at an `=`, trimmed, split across lines, or read as a
+/// comment or section header.
+fn needs_key_quotes(key: &str) -> bool {
+    key.contains(['=', '\r', '\n']) || key.trim() != key || key.starts_with(['#', ';', '[', '"'])
+}
+
+/// Whether a config value must be quoted to parse back unchanged.
+fn needs_config_quotes(value: &str) -> bool {
+    value.contains(['\r', '\n'])
+        || value.trim() != value
+        || value.starts_with('"')
+        || strip_inline_comment(value) != value
+}
+
+/// `value` as a double-quoted config string.
+fn quote_config(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Whether a CSV field must be quoted to parse back unchanged.
+fn needs_quotes(field: &str) -> bool {
+    field.contains([',', '"', '\r', '\n']) || field.trim() != field
+}
+
+fn quote_csv(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Compact JSON; NaN and infinities are written as `null`.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) if n.is_finite() => write!(f, "{}", n),
+            JsonValue::Number(_) => f.write_str("null"),
+            JsonValue::String(s) => write_json_string(f, s),
+            JsonValue::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            JsonValue::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, item)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_json_string(f, key)?;
+                    f.write_str(":")?;
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn sorted<'a>(keys: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut keys: Vec<String> = keys.cloned().collect();
+    keys.sort();
+    keys
+}
+
 /// Print a summary of the parsed result.
 pub fn summarise(data: &ParsedData) {
     match data {
-        ParsedData::Config(map) => {
-            println!("Parsed config: {} values loaded", map.len());
+        ParsedData::Config(_) | ParsedData::MultiConfig(_) => {
+            println!("Parsed config: {} values loaded", data.row_count());
         }
         ParsedData::JsonRecords(records) => {
             println!("Parsed JSON: {} records loaded", records.len());
         }
+        ParsedData::JsonValue(value) => match value {
+            JsonValue::Array(items) => println!("Parsed JSON: array of {} values", items.len()),
+            JsonValue::Object(fields) => println!("Parsed JSON: object with {} keys", fields.len()),
+            _ => println!("Parsed JSON: single value"),
+        },
         ParsedData::CsvTable { headers, rows } => {
             println!(
                 "Parsed CSV: {} columns, {} rows",
@@ -32,3 +399,241 @@ pub fn summarise(data: &ParsedData) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn accessors_per_variant() {
+        let config = ParsedData::Config(strings(&[("network.port", "8080"), ("name", "app")]));
+        assert_eq!(config.get("network.port"), Some("8080"));
+        assert_eq!(config.get("port"), None);
+        assert_eq!(config.row_count(), 2);
+        assert_eq!(config.keys(), ["name", "network.port"]);
+
+        let multi = ParsedData::MultiConfig(HashMap::from([
+            (
+                "port".to_string(),
+                vec!["80".to_string(), "8080".to_string()],
+            ),
+            ("host".to_string(), vec!["a".to_string()]),
+        ]));
+        assert_eq!(multi.get("port"), Some("8080"));
+        assert_eq!(multi.get_list("port"), Some(vec!["80", "8080"]));
+        assert_eq!(multi.row_count(), 2);
+        assert_eq!(multi.keys(), ["host", "port"]);
+
+        let records = ParsedData::JsonRecords(vec![
+            strings(&[("id", "1"), ("name", "a")]),
+            strings(&[("id", "2"), ("extra", "x")]),
+        ]);
+        assert_eq!(records.get("id"), Some("1"));
+        assert_eq!(records.get("extra"), None);
+        assert_eq!(records.row_count(), 2);
+        assert_eq!(records.keys(), ["extra", "id", "name"]);
+
+        let object = ParsedData::JsonValue(JsonValue::Object(BTreeMap::from([
+            ("name".to_string(), JsonValue::String("app".into())),
+            ("port".to_string(), JsonValue::Number(8080.0)),
+        ])));
+        assert_eq!(object.get("name"), Some("app"));
+        assert_eq!(object.get("port"), None);
+        assert_eq!(object.row_count(), 1);
+        assert_eq!(object.keys(), ["name", "port"]);
+
+        let table = ParsedData::CsvTable {
+            headers: vec!["b".into(), "a".into()],
+            rows: vec![vec!["1".into(), "2".into()]],
+        };
+        assert_eq!(table.get("a"), None);
+        assert_eq!(table.row_count(), 1);
+        assert_eq!(table.keys(), ["b", "a"]);
+    }
+
+    #[test]
+    fn comma_separated_lists() {
+        let data =
+            crate::parser::parse("[network]\nallowed_hosts = alpha, beta\nport = 8080\n").unwrap();
+        assert_eq!(
+            data.get_list("network.allowed_hosts"),
+            Some(vec!["alpha", "beta"])
+        );
+        assert_eq!(data.get_list("network.port"), Some(vec!["8080"]));
+        assert_eq!(data.get_list("network.missing"), None);
+
+        let config = ParsedData::Config(strings(&[("hosts", " a ,, b ,"), ("none", "")]));
+        assert_eq!(config.get_list("hosts"), Some(vec!["a", "b"]));
+        assert_eq!(config.get_list("none"), Some(vec![]));
+    }
+
+    #[test]
+    fn to_string_round_trips() {
+        use crate::parser::{
+            parse, parse_config_with, parse_with_format, ConfigOptions, DuplicateKeyPolicy, Format,
+        };
+
+        let configs = [
+            strings(&[("name", "app"), ("metadata.version", "1.0")]),
+            strings(&[
+                ("network.allowed_hosts", "alpha, beta"),
+                ("network.url", "http://x/?a=b"),
+                ("a.b.c", "d"),
+                ("empty", ""),
+            ]),
+            strings(&[("hosts", "a, b")]),
+            strings(&[
+                ("padded", " padded"),
+                ("net.lines", "one\ntwo\r\n"),
+                ("net.quoted", "\"x\" \\ \"y\""),
+                ("net.tab", "\t"),
+            ]),
+            strings(&[
+                ("note", "x ; y"),
+                ("net.path", "C:\\dir #1"),
+                ("net.lead", "; all comment"),
+                ("net.mixed", " a\" ; b"),
+                ("net.single", "'a' # b"),
+                ("net.url", "http://x/#frag"),
+            ]),
+            strings(&[
+                (".x", "1"),
+                ("a=b", "1"),
+                ("#k", "v"),
+                (";k", "v"),
+                ("[k", "v]"),
+                ("\"q\"", "v"),
+                (" pad ", "v"),
+                ("line\nbreak", "v"),
+                ("net.=", "v"),
+                ("net.[x", "y]"),
+                (" net.k", "v"),
+                ("net .k", "v"),
+                ("", "empty key"),
+            ]),
+            strings(&[("[x", "1")]),
+        ];
+        for map in configs {
+            let data = ParsedData::Config(map);
+            assert_eq!(parse(&data.to_string()).unwrap(), data, "{}", data);
+        }
+        let dotted = ParsedData::Config(strings(&[(".x", "1")]));
+        assert_eq!(dotted.to_string(), ".x = 1\n");
+        let yaml = crate::yaml_parser::parse_yaml_lite("a=b: 1\n").unwrap();
+        assert_eq!(yaml.to_string(), "\"a=b\" = 1\n");
+        assert_eq!(parse(&yaml.to_string()).unwrap(), yaml);
+        let hosts = ParsedData::Config(strings(&[("hosts", "a, b")]));
+        assert_eq!(hosts.to_string(), "; config\nhosts = a, b\n");
+
+        let multi = ParsedData::MultiConfig(HashMap::from([
+            (
+                "net.port".to_string(),
+                vec!["80".to_string(), "8080".to_string()],
+            ),
+            ("name".to_string(), vec!["app".to_string()]),
+        ]));
+        let text = multi.to_string();
+        assert_eq!(text, "name = app\n\n[net]\nport = 80\nport = 8080\n");
+        assert_eq!(
+            parse_config_with(
+                &text,
+                &ConfigOptions {
+                    duplicate_keys: DuplicateKeyPolicy::Collect,
+                    ..ConfigOptions::default()
+                }
+            )
+            .unwrap(),
+            multi
+        );
+
+        let tables = [
+            (vec!["id", "note"], vec![vec!["1", "plain"], vec!["2", ""]]),
+            (
+                vec!["time: now", "[x"],
+                vec![vec!["a, b", "say \"hi\""], vec![" padded ", "two\r\nlines"]],
+            ),
+            (vec!["a", "b"], vec![]),
+        ];
+        for (headers, rows) in tables {
+            let data = ParsedData::CsvTable {
+                headers: headers.into_iter().map(String::from).collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|row| row.into_iter().map(String::from).collect())
+                    .collect(),
+            };
+            assert_eq!(parse(&data.to_string()).unwrap(), data, "{}", data);
+        }
+
+        let record_sets = [
+            vec![],
+            vec![strings(&[
+                ("id", "1"),
+                ("msg", "a \"quoted\", \\ line\n\u{1}"),
+            ])],
+            vec![
+                strings(&[("id", "1")]),
+                strings(&[]),
+                strings(&[("x", "é")]),
+            ],
+        ];
+        for records in record_sets {
+            let data = ParsedData::JsonRecords(records);
+            assert_eq!(parse(&data.to_string()).unwrap(), data, "{}", data);
+        }
+
+        let value = ParsedData::JsonValue(JsonValue::Object(BTreeMap::from([
+            ("n".to_string(), JsonValue::Number(-2.5e-3)),
+            (
+                "list".to_string(),
+                JsonValue::Array(vec![JsonValue::Null, JsonValue::Bool(false)]),
+            ),
+        ])));
+        assert_eq!(value.to_string(), "{\"list\":[null,false],\"n\":-0.0025}\n");
+        assert_eq!(
+            parse_with_format(&value.to_string(), Format::Json).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn to_json_exports_valid_json() {
+        use crate::json_parser::parse_json_value;
+
+        let table =
+            crate::parser::parse("id,note\n1,\"say \"\"hi\"\", then\nleave\"\n2,\\path\n").unwrap();
+        let json = table.to_json();
+        let row = |id: &str, note: &str| {
+            JsonValue::Object(BTreeMap::from([
+                ("id".to_string(), JsonValue::String(id.into())),
+                ("note".to_string(), JsonValue::String(note.into())),
+            ]))
+        };
+        assert_eq!(
+            parse_json_value(&json).unwrap(),
+            JsonValue::Array(vec![
+                row("1", "say \"hi\", then\nleave"),
+                row("2", "\\path")
+            ])
+        );
+
+        let config = ParsedData::Config(strings(&[("net.port", "8080"), ("name", "app")]));
+        assert_eq!(config.to_json(), r#"{"name":"app","net.port":"8080"}"#);
+
+        let multi = ParsedData::MultiConfig(HashMap::from([(
+            "port".to_string(),
+            vec!["80".to_string(), "8080".to_string()],
+        )]));
+        assert_eq!(multi.to_json(), r#"{"port":["80","8080"]}"#);
+
+        let records = ParsedData::JsonRecords(vec![strings(&[("a", "\t1")])]);
+        assert_eq!(records.to_json(), r#"[{"a":"\t1"}]"#);
+    }
+}
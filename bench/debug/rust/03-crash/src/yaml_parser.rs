@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::parser::{Format, ParseError};
+use crate::processor::ParsedData;
+
+/// Parse a small indentation-based subset of YAML:
+///
+/// ```yaml
+/// name: test_app
+/// network:
+///   port: 8080
+///   timeout: 30
+/// ```
+///
+/// Top-level `key: value` pairs are stored as-is.  A key with no value
+/// starts a map whose entries, indented by two spaces, are stored as
+/// `parent.child` (the same flattening the INI parser uses for sections).
+/// A parent with no entries holds an empty string.  Values may be wrapped
+/// in matching single or double quotes.  Blank lines and `#` comment lines
+/// are skipped.
+///
+/// Only scalars and one level of nesting are supported: lists, anchors,
+/// deeper nesting or any other indentation is an error.
+pub fn parse_yaml_lite(content: &str) -> Result<ParsedData, ParseError> {
+    let mut map = HashMap::new();
+    // The key opened by a `key:` line and whether it has entries yet.
+    let mut parent: Option<(String, bool)> = None;
+
+    for (line_no, line) in content.lines().enumerate() {
+        let fail =
+            |message: &str| ParseError::new(Format::Yaml, message.into()).at_line(line_no + 1);
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if line[indent..].starts_with('\t') {
+            return Err(fail("tabs are not allowed in indentation"));
+        }
+        if trimmed.starts_with('-') {
+            return Err(fail("lists are not supported"));
+        }
+        let (key, value) = split_pair(trimmed).ok_or_else(|| fail("expected `key: value`"))?;
+        if value.starts_with('&') || value.starts_with('*') {
+            return Err(fail("anchors and aliases are not supported"));
+        }
+
+        match indent {
+            0 => {
+                if let Some((previous, false)) = parent.take() {
+                    map.insert(previous, String::new());
+                }
+                if value.is_empty() {
+                    parent = Some((key.to_string(), false));
+                } else {
+                    map.insert(key.to_string(), unquote(value).to_string());
+                }
+            }
+            2 => {
+                let Some((name, has_entries)) = parent.as_mut() else {
+                    return Err(fail("indented line without a parent key"));
+                };
+                if value.is_empty() {
+                    return Err(fail("only one level of nesting is supported"));
+                }
+                *has_entries = true;
+                map.insert(format!("{}.{}", name, key), unquote(value).to_string());
+            }
+            _ => return Err(fail("indentation must be zero or two spaces")),
+        }
+    }
+
+    if let Some((previous, false)) = parent {
+        map.insert(previous, String::new());
+    }
+
+    Ok(ParsedData::Config(map))
+}
+
+/// Split `key: value` (or a bare `key:`) at the first colon that is
+/// followed by whitespace or the end of the line.
+pub(crate) fn split_pair(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || !(value.is_empty() || value.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> HashMap<String, String> {
+        match parse_yaml_lite(content) {
+            Ok(ParsedData::Config(map)) => map,
+            other => panic!("expected a config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nested_keys_are_flattened() {
+        let map = config(
+            "# app\nname: test_app\nnetwork:\n  allowed_hosts: alpha, beta\n\n  port: \"8080\"\nempty:\nurl: http://x:1\n",
+        );
+        assert_eq!(map.len(), 5);
+        assert_eq!(map["name"], "test_app");
+        assert_eq!(map["network.allowed_hosts"], "alpha, beta");
+        assert_eq!(map["network.port"], "8080");
+        assert_eq!(map["empty"], "");
+        assert_eq!(map["url"], "http://x:1");
+    }
+
+    #[test]
+    fn unsupported_constructs_are_errors() {
+        let cases = [
+            (
+                "a:\n  b:\n    c: 1\n",
+                2,
+                "only one level of nesting is supported",
+            ),
+            ("a:\n  - 1\n", 2, "lists are not supported"),
+            (
+                "a: 1\n   b: 2\n",
+                2,
+                "indentation must be zero or two spaces",
+            ),
+            ("  b: 2\n", 1, "indented line without a parent key"),
+            (
+                "# x\n\na: &x 1\n",
+                3,
+                "anchors and aliases are not supported",
+            ),
+            ("just text\n", 1, "expected `key: value`"),
+        ];
+        for (content, line, message) in cases {
+            let err = parse_yaml_lite(content).unwrap_err();
+            assert_eq!(err.format, Format::Yaml);
+            assert_eq!(err.message, message, "{:?}", content);
+            assert_eq!(err.line, Some(line), "{:?}", content);
+        }
+    }
+}
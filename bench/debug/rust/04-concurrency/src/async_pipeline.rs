@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::mpsc::{self, error::TryRecvError, Receiver, Sender};
+use tokio::sync::Mutex;
+
+use crate::error::PipelineError;
+use crate::pipeline::{PipelineConfig, PipelineOutput, PipelineStats};
+use crate::stage::{route, Collector, RetryPredicate, Route, StageStats, POLL_INTERVAL};
+use crate::worker::{do_work, Record};
+
+/// Async counterpart of `run_pipeline`, for callers already inside a Tokio
+/// runtime.
+///
+/// Same topology, retry feedback, dead-letter routing and shutdown
+/// handling as the threaded pipeline, but every channel is a bounded
+/// `tokio::sync::mpsc` channel and every stage (and each Stage 2 worker)
+/// is a task rather than an OS thread.  The producer runs on the calling
+/// task.  Every `PipelineConfig` field is honoured.
+///
+/// Returns `PipelineError::ZeroChannelBound` if `config.channel_bound` is
+/// 0 and `PipelineError::ZeroWorkers` if `config.stage2_workers` is 0.
+#[allow(dead_code)]
+pub async fn run_pipeline_async(config: PipelineConfig) -> Result<PipelineOutput, PipelineError> {
+    if config.channel_bound == 0 {
+        return Err(PipelineError::ZeroChannelBound);
+    }
+    if config.stage2_workers == 0 {
+        return Err(PipelineError::ZeroWorkers);
+    }
+    let started = Instant::now();
+    let bound = config.channel_bound;
+
+    let (input_tx, input_rx) = mpsc::channel::<Record>(bound);
+    let (s1_to_s2_tx, s1_to_s2_rx) = mpsc::channel::<Record>(bound);
+    let (s2_to_s3_tx, s2_to_s3_rx) = mpsc::channel::<Record>(bound);
+    let (feedback_tx, feedback_rx) = mpsc::channel::<Record>(bound);
+    let (dead_letter_tx, dead_letter_rx) = if config.dead_letter {
+        let (tx, rx) = mpsc::channel::<Record>(bound);
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let shutdown = config.shutdown.unwrap_or_default();
+
+    let s1 = tokio::spawn(stage1(
+        input_rx,
+        s1_to_s2_tx,
+        feedback_rx,
+        Arc::clone(&in_flight),
+        Arc::clone(&shutdown),
+    ));
+    let s2 = tokio::spawn(stage2(
+        s1_to_s2_rx,
+        s2_to_s3_tx,
+        feedback_tx,
+        dead_letter_tx,
+        config.retry_if,
+        in_flight,
+        Arc::clone(&shutdown),
+        config.stage2_workers,
+    ));
+    let s3 = tokio::spawn(stage3(s2_to_s3_rx, config.ordered_output));
+    let dead_letters = tokio::spawn(async move {
+        let mut records = Vec::new();
+        if let Some(mut rx) = dead_letter_rx {
+            while let Some(record) = rx.recv().await {
+                records.push(record);
+            }
+        }
+        records
+    });
+
+    // --- Producer: feed records into Stage 1 ---
+    for i in 1..=config.num_records {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let record = Record::new(i).with_checksum(config.checksum);
+        if input_tx.send(record).await.is_err() {
+            break; // Stage 1 has failed; awaiting it below reports why.
+        }
+    }
+    drop(input_tx);
+
+    // Report the furthest-downstream failure, as `run_pipeline` does.
+    let s1 = s1.await;
+    let s2 = s2.await;
+    let s3 = s3.await;
+    let dead_letters = dead_letters.await;
+
+    let dead_letters =
+        dead_letters.map_err(|_| PipelineError::Panicked { stage: "dead-letter" })?;
+    let (records, stage3) = s3.map_err(|_| PipelineError::Panicked { stage: "stage3" })?;
+    let stage2 = s2.map_err(|_| PipelineError::Panicked { stage: "stage2" })??;
+    let stage1 = s1.map_err(|_| PipelineError::Panicked { stage: "stage1" })??;
+
+    Ok(PipelineOutput {
+        dead_letters,
+        stats: PipelineStats {
+            stage1,
+            stage2,
+            stage3,
+            elapsed: started.elapsed(),
+            ..PipelineStats::default()
+        }
+        .with_latencies(&records),
+        records,
+    })
+}
+
+/// Async Stage 1; see `stage::stage1`.
+///
+/// Instead of polling with timeouts it waits on whichever of "room in
+/// `output`" and "new input" applies, plus a short sleep so that feedback
+/// and the in-flight count are rechecked regularly.
+async fn stage1(
+    mut input: Receiver<Record>,
+    output: Sender<Record>,
+    mut feedback_rx: Receiver<Record>,
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<StageStats, PipelineError> {
+    let started = Instant::now();
+    let disconnected = PipelineError::Disconnected { stage: "stage1" };
+    let mut stats = StageStats::default();
+    let mut pending: VecDeque<Record> = VecDeque::new();
+    let mut input_open = true;
+
+    'run: while !shutdown.load(Ordering::SeqCst) {
+        // Read before draining feedback, for the same reason as the
+        // threaded Stage 1.
+        let settled =
+            !input_open && pending.is_empty() && in_flight.load(Ordering::SeqCst) == 0;
+        let mut progressed = false;
+
+        loop {
+            match feedback_rx.try_recv() {
+                Ok(mut record) => {
+                    record.mark_retry();
+                    do_work(&mut record, "stage1-redo");
+                    pending.push_back(record);
+                    stats.feedback += 1;
+                    progressed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) if shutdown.load(Ordering::SeqCst) => {
+                    break 'run;
+                }
+                Err(TryRecvError::Disconnected) => return Err(disconnected),
+            }
+        }
+
+        if settled && !progressed {
+            break;
+        }
+
+        tokio::select! {
+            permit = output.reserve(), if !pending.is_empty() => {
+                let Ok(permit) = permit else {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break 'run;
+                    }
+                    return Err(disconnected);
+                };
+                if let Some(record) = pending.pop_front() {
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    permit.send(record);
+                    stats.forwarded += 1;
+                }
+            }
+            record = input.recv(), if input_open && pending.is_empty() => match record {
+                Some(mut record) => {
+                    do_work(&mut record, "stage1");
+                    pending.push_back(record);
+                    stats.processed += 1;
+                }
+                None => input_open = false,
+            },
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+
+    drop(output);
+    stats.elapsed = started.elapsed();
+
+    eprintln!(
+        "[stage1] finished: processed={}, forwarded={}, feedback={}",
+        stats.processed, stats.forwarded, stats.feedback
+    );
+    Ok(stats)
+}
+
+/// Async Stage 2 as `workers` tasks sharing `input`; see `stage::stage2`.
+#[allow(clippy::too_many_arguments)]
+async fn stage2(
+    input: Receiver<Record>,
+    output: Sender<Record>,
+    feedback_tx: Sender<Record>,
+    dead_letter: Option<Sender<Record>>,
+    retry_if: RetryPredicate,
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+    workers: usize,
+) -> Result<StageStats, PipelineError> {
+    let started = Instant::now();
+    let input = Arc::new(Mutex::new(input));
+
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            tokio::spawn(stage2_worker(
+                Arc::clone(&input),
+                output.clone(),
+                feedback_tx.clone(),
+                dead_letter.clone(),
+                Arc::clone(&retry_if),
+                Arc::clone(&in_flight),
+                Arc::clone(&shutdown),
+            ))
+        })
+        .collect();
+
+    // Only the workers' clones should keep the channels open.
+    drop(output);
+    drop(feedback_tx);
+    drop(dead_letter);
+
+    let mut stats = StageStats::default();
+    let mut first_error = None;
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(worker_stats)) => stats.add(worker_stats),
+            Ok(Err(e)) => {
+                first_error.get_or_insert(e);
+            }
+            Err(_) => {
+                first_error.get_or_insert(PipelineError::Panicked { stage: "stage2" });
+            }
+        }
+    }
+
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    stats.elapsed = started.elapsed();
+
+    eprintln!(
+        "[stage2] finished: processed={}, forwarded={}, feedback={}, dead_lettered={}, workers={}",
+        stats.processed, stats.forwarded, stats.feedback, stats.dead_lettered, workers
+    );
+    Ok(stats)
+}
+
+async fn stage2_worker(
+    input: Arc<Mutex<Receiver<Record>>>,
+    output: Sender<Record>,
+    feedback_tx: Sender<Record>,
+    dead_letter: Option<Sender<Record>>,
+    retry_if: RetryPredicate,
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<StageStats, PipelineError> {
+    let mut stats = StageStats::default();
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let next = input.lock().await.recv().await;
+        let Some(mut record) = next else { break };
+        stats.processed += 1;
+        do_work(&mut record, "stage2");
+
+        let route = route(&record, retry_if(&record), dead_letter.is_some());
+        let sent = match (&route, &dead_letter) {
+            (Route::Feedback, _) => feedback_tx.send(record).await.is_ok(),
+            (Route::DeadLetter, Some(dead_letter)) => dead_letter.send(record).await.is_ok(),
+            _ => output.send(record).await.is_ok(),
+        };
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        if !sent {
+            // Stage 1 leaves without waiting for us when shutting down.
+            if shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            return Err(PipelineError::Disconnected { stage: "stage2" });
+        }
+        match route {
+            Route::Output => stats.forwarded += 1,
+            Route::Feedback => stats.feedback += 1,
+            Route::DeadLetter => stats.dead_lettered += 1,
+        }
+    }
+    Ok(stats)
+}
+
+/// Async Stage 3; see `stage::stage3`.
+async fn stage3(mut input: Receiver<Record>, ordered: bool) -> (Vec<Record>, StageStats) {
+    let mut collector = Collector::new(ordered);
+    while let Some(record) = input.recv().await {
+        collector.push(record);
+    }
+    collector.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::{check_completeness, validate_batch};
+    use std::time::Duration;
+
+    const NUM_RECORDS: u32 = 500;
+
+    /// Run the async pipeline, failing if it takes over 5s.
+    async fn run_with_timeout(config: PipelineConfig) -> PipelineOutput {
+        tokio::time::timeout(Duration::from_secs(5), run_pipeline_async(config))
+            .await
+            .expect("pipeline deadlocked (no result within 5s)")
+            .expect("pipeline failed")
+    }
+
+    fn assert_complete(output: &PipelineOutput) {
+        let records = &output.records;
+        let (missing, duplicates) = check_completeness(records, NUM_RECORDS);
+        assert!(missing.is_empty(), "missing: {:?}", missing);
+        assert!(duplicates.is_empty(), "duplicates: {:?}", duplicates);
+        assert_eq!(validate_batch(records).0, NUM_RECORDS as usize);
+        for r in records {
+            assert_eq!(r.retry_count, u32::from(r.id % 10 == 0), "record {}", r.id);
+        }
+        assert_eq!(output.stats.stage2.feedback, NUM_RECORDS / 10);
+    }
+
+    #[tokio::test]
+    async fn completes_on_current_thread_runtime() {
+        for channel_bound in [1, 5] {
+            let output = run_with_timeout(PipelineConfig {
+                channel_bound,
+                ..PipelineConfig::default()
+            })
+            .await;
+            assert_complete(&output);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn completes_with_stage2_workers() {
+        for channel_bound in [1, 5] {
+            let output = run_with_timeout(PipelineConfig {
+                channel_bound,
+                stage2_workers: 4,
+                ordered_output: true,
+                ..PipelineConfig::default()
+            })
+            .await;
+            assert_complete(&output);
+
+            let ids: Vec<u32> = output.records.iter().map(|r| r.id).collect();
+            assert_eq!(ids, (1..=NUM_RECORDS).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn dead_letters_exhausted_retries() {
+        let output = run_with_timeout(PipelineConfig {
+            dead_letter: true,
+            ..PipelineConfig::default()
+        })
+        .await;
+
+        assert_eq!(output.records.len(), (NUM_RECORDS - NUM_RECORDS / 10) as usize);
+        assert_eq!(output.dead_letters.len(), (NUM_RECORDS / 10) as usize);
+        assert!(output.dead_letters.iter().all(|r| r.id % 10 == 0));
+    }
+
+    #[tokio::test]
+    async fn zero_channel_bound_is_rejected() {
+        let result = run_pipeline_async(PipelineConfig {
+            num_records: 10,
+            channel_bound: 0,
+            ..PipelineConfig::default()
+        })
+        .await;
+        assert_eq!(result.err(), Some(PipelineError::ZeroChannelBound));
+    }
+
+    #[tokio::test]
+    async fn zero_workers_is_rejected() {
+        let result = run_pipeline_async(PipelineConfig {
+            num_records: 10,
+            stage2_workers: 0,
+            ..PipelineConfig::default()
+        })
+        .await;
+        assert_eq!(result.err(), Some(PipelineError::ZeroWorkers));
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+
+/// Why the pipeline stopped before delivering every record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineError {
+    /// `stage` could not send because the stage on the other end of the
+    /// channel had already gone away.
+    Disconnected { stage: &'static str },
+    /// `stage` panicked; its thread was joined with an error.
+    Panicked { stage: &'static str },
+    /// `PipelineConfig::channel_bound` was 0; every channel needs room for
+    /// at least one record.
+    ZeroChannelBound,
+    /// `PipelineConfig::stage2_workers` was 0, so nothing would run Stage 2.
+    ZeroWorkers,
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Disconnected { stage } => {
+                write!(f, "{}: peer stage disconnected", stage)
+            }
+            PipelineError::Panicked { stage } => write!(f, "{} panicked", stage),
+            PipelineError::ZeroChannelBound => write!(f, "channel_bound must be at least 1"),
+            PipelineError::ZeroWorkers => write!(f, "stage2_workers must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
@@ -1,6 +1,9 @@
+mod error;
 mod worker;
 mod stage;
 mod pipeline;
+#[cfg(feature = "tokio")]
+mod async_pipeline;
 
 use std::sync::mpsc;
 use std::thread;
@@ -16,14 +19,22 @@ fn main() {
     let (result_tx, result_rx) = mpsc::channel();
 
     let handle = thread::spawn(move || {
-        let results = pipeline::run_pipeline();
+        let results = pipeline::run_pipeline_default();
         let _ = result_tx.send(results);
     });
 
     // Wait up to 5 seconds.
     match result_rx.recv_timeout(Duration::from_secs(5)) {
-        Ok(results) => {
-            report_results(&results);
+        Ok(Ok(output)) => {
+            report_results(&output.records);
+            if !output.dead_letters.is_empty() {
+                eprintln!("WARNING: {} records dead-lettered", output.dead_letters.len());
+            }
+            report_stats(&output.stats);
+        }
+        Ok(Err(e)) => {
+            eprintln!("ERROR: Pipeline failed: {}", e);
+            std::process::exit(1);
         }
         Err(mpsc::RecvTimeoutError::Timeout) => {
             eprintln!("ERROR: Pipeline timed out after 5s");
@@ -72,3 +83,24 @@ fn report_results(results: &[worker::Record]) {
         );
     }
 }
+
+/// Print per-stage timing to stderr.
+fn report_stats(stats: &pipeline::PipelineStats) {
+    for (name, stage) in [
+        ("stage1", &stats.stage1),
+        ("stage2", &stats.stage2),
+        ("stage3", &stats.stage3),
+    ] {
+        eprintln!(
+            "[{}] {} records in {:?} ({:.0} records/s)",
+            name,
+            stage.processed,
+            stage.elapsed,
+            stage.throughput()
+        );
+    }
+    eprintln!("Pipeline finished in {:?}", stats.elapsed);
+    if let (Some(p50), Some(p99)) = (stats.latency_p50, stats.latency_p99) {
+        eprintln!("Record latency: p50={:?}, p99={:?}", p50, p99);
+    }
+}
@@ -1,8 +1,12 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::stage;
-use crate::worker::Record;
+use crate::error::PipelineError;
+use crate::stage::{self, RetryPredicate, StageStats};
+use crate::worker::{ByteSum, Checksum, ChecksumFn, Record};
 
 /// Channel buffer size.
 const CHANNEL_BOUND: usize = 5;
@@ -10,10 +14,29 @@ const CHANNEL_BOUND: usize = 5;
 /// Total records to push through the pipeline.
 const NUM_RECORDS: u32 = 500;
 
-/// Configuration for the pipeline (extracted for clarity).
-struct PipelineConfig {
-    num_records: u32,
-    channel_bound: usize,
+/// Configuration for the pipeline.
+pub struct PipelineConfig {
+    /// Records the producer feeds in, with ids `1..=num_records`.
+    pub num_records: u32,
+    /// Capacity of every channel between stages; must be at least 1.
+    pub channel_bound: usize,
+    /// Checksum the records are stamped and verified with, e.g.
+    /// `Crc32::compute`.
+    pub checksum: ChecksumFn,
+    /// Threads running Stage 2; more than one uses `stage2_pool`, which
+    /// does not preserve output order (see `ordered_output`).
+    pub stage2_workers: usize,
+    /// Return results sorted by record id rather than in arrival order.
+    pub ordered_output: bool,
+    /// Set this flag to stop the pipeline early; `run_pipeline` then
+    /// returns the records Stage 3 had collected.
+    pub shutdown: Option<Arc<AtomicBool>>,
+    /// Collect records that still need a retry after the last allowed one
+    /// into `PipelineOutput::dead_letters` instead of the normal results.
+    pub dead_letter: bool,
+    /// Which records Stage 2 sends back for a retry, at most until they
+    /// reach `MAX_RETRY_STAGE`.  Defaults to `stage::retry_every_tenth`.
+    pub retry_if: RetryPredicate,
 }
 
 impl Default for PipelineConfig {
@@ -21,11 +44,60 @@ impl Default for PipelineConfig {
         PipelineConfig {
             num_records: NUM_RECORDS,
             channel_bound: CHANNEL_BOUND,
+            checksum: ByteSum::compute,
+            stage2_workers: 1,
+            ordered_output: false,
+            shutdown: None,
+            dead_letter: false,
+            retry_if: Arc::new(stage::retry_every_tenth),
         }
     }
 }
 
-/// Build and run the 3-stage pipeline, returning collected results.
+/// Per-stage statistics for one pipeline run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub stage1: StageStats,
+    pub stage2: StageStats,
+    pub stage3: StageStats,
+    /// Wall-clock time for the whole run, including the producer.
+    pub elapsed: Duration,
+    /// Median end-to-end record latency; `None` if nothing was collected.
+    pub latency_p50: Option<Duration>,
+    /// 99th-percentile end-to-end record latency.
+    pub latency_p99: Option<Duration>,
+}
+
+impl PipelineStats {
+    /// Fill in the latency percentiles from the collected `records`.
+    pub(crate) fn with_latencies(mut self, records: &[Record]) -> Self {
+        let mut latencies: Vec<Duration> = records.iter().filter_map(Record::latency).collect();
+        latencies.sort_unstable();
+        self.latency_p50 = percentile(&latencies, 50);
+        self.latency_p99 = percentile(&latencies, 99);
+        self
+    }
+}
+
+/// Nearest-rank percentile of already-sorted `values`.
+fn percentile(values: &[Duration], pct: usize) -> Option<Duration> {
+    let rank = (values.len() * pct).div_ceil(100);
+    values.get(rank.max(1) - 1).copied()
+}
+
+/// What a pipeline run produced.
+#[derive(Debug)]
+pub struct PipelineOutput {
+    /// Records collected by Stage 3.
+    pub records: Vec<Record>,
+    /// Records that exhausted their retries; always empty unless
+    /// `PipelineConfig::dead_letter` was set.
+    pub dead_letters: Vec<Record>,
+    pub stats: PipelineStats,
+}
+
+/// Build and run the 3-stage pipeline, returning collected results and
+/// per-stage statistics.
 ///
 /// The pipeline topology:
 ///
@@ -35,10 +107,31 @@ impl Default for PipelineConfig {
 ///                                |--- [feedback] ----------|
 /// ```
 ///
-/// All channels are `sync_channel` with a small bound.
-pub fn run_pipeline() -> Vec<Record> {
-    let config = PipelineConfig::default();
+/// All channels are `sync_channel`s bounded by `config.channel_bound`.
+///
+/// If `config.shutdown` is set mid-run, the producer and Stages 1 and 2
+/// stop after the record in hand and Stage 3 collects whatever was already
+/// on its way.  The partial results are returned as `Ok`; records that
+/// were queued between stages at the time are dropped.
+///
+/// If a stage fails, returns the error from the furthest-downstream stage
+/// that failed, since upstream stages usually fail only because a later
+/// one went away.
+///
+/// Returns `PipelineError::ZeroChannelBound` if `config.channel_bound` is
+/// 0 and `PipelineError::ZeroWorkers` if `config.stage2_workers` is 0.
+pub fn run_pipeline(config: PipelineConfig) -> Result<PipelineOutput, PipelineError> {
+    let started = Instant::now();
+    if config.channel_bound == 0 {
+        return Err(PipelineError::ZeroChannelBound);
+    }
+    if config.stage2_workers == 0 {
+        return Err(PipelineError::ZeroWorkers);
+    }
     let bound = config.channel_bound;
+    let workers = config.stage2_workers;
+    let retry_if = config.retry_if;
+    let ordered = config.ordered_output;
 
     // Forward channels (bounded).
     let (input_tx, input_rx) = sync_channel::<Record>(bound);
@@ -48,40 +141,354 @@ pub fn run_pipeline() -> Vec<Record> {
     // Feedback channel (bounded).
     let (feedback_tx, feedback_rx) = sync_channel::<Record>(bound);
 
+    // Dead-letter channel (bounded), drained by its own collector.
+    let (dead_letter_tx, dead_letter_rx) = if config.dead_letter {
+        let (tx, rx) = sync_channel::<Record>(bound);
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+
+    // Records Stage 1 has handed to Stage 2 that Stage 2 hasn't finished.
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let s2_in_flight = Arc::clone(&in_flight);
+
+    let shutdown = config.shutdown.unwrap_or_default();
+    let s1_shutdown = Arc::clone(&shutdown);
+    let s2_shutdown = Arc::clone(&shutdown);
+
     // --- Spawn pipeline stages ---
 
     let s1 = thread::Builder::new()
         .name("stage-1".into())
-        .spawn(move || {
-            stage::stage1(input_rx, s1_to_s2_tx, feedback_rx);
-        })
+        .spawn(move || stage::stage1(input_rx, s1_to_s2_tx, feedback_rx, in_flight, s1_shutdown))
         .expect("failed to spawn stage 1");
 
     let s2 = thread::Builder::new()
         .name("stage-2".into())
         .spawn(move || {
-            stage::stage2(s1_to_s2_rx, s2_to_s3_tx, feedback_tx);
+            if workers == 1 {
+                stage::stage2(
+                    s1_to_s2_rx,
+                    s2_to_s3_tx,
+                    feedback_tx,
+                    dead_letter_tx,
+                    retry_if,
+                    s2_in_flight,
+                    s2_shutdown,
+                )
+            } else {
+                stage::stage2_pool(
+                    s1_to_s2_rx,
+                    s2_to_s3_tx,
+                    feedback_tx,
+                    dead_letter_tx,
+                    retry_if,
+                    s2_in_flight,
+                    s2_shutdown,
+                    workers,
+                )
+            }
         })
         .expect("failed to spawn stage 2");
 
     let s3 = thread::Builder::new()
         .name("stage-3".into())
-        .spawn(move || -> Vec<Record> {
-            stage::stage3(s2_to_s3_rx)
-        })
+        .spawn(move || stage::stage3(s2_to_s3_rx, ordered))
         .expect("failed to spawn stage 3");
 
+    let dead_letters = thread::Builder::new()
+        .name("dead-letter".into())
+        .spawn(move || -> Vec<Record> { dead_letter_rx.into_iter().flatten().collect() })
+        .expect("failed to spawn dead-letter collector");
+
     // --- Producer: feed records into Stage 1 ---
     for i in 1..=config.num_records {
-        let record = Record::new(i);
-        input_tx.send(record).expect("producer send failed");
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let record = Record::new(i).with_checksum(config.checksum);
+        if input_tx.send(record).is_err() {
+            break; // Stage 1 has failed; its join below reports why.
+        }
     }
     drop(input_tx); // close the input channel to signal EOF
 
     // --- Wait for the pipeline to complete ---
-    s1.join().expect("stage 1 panicked");
-    s2.join().expect("stage 2 panicked");
-    let results = s3.join().expect("stage 3 panicked");
+    let s1 = s1.join();
+    let s2 = s2.join();
+    let s3 = s3.join();
+    let dead_letters = dead_letters.join();
+
+    let dead_letters =
+        dead_letters.map_err(|_| PipelineError::Panicked { stage: "dead-letter" })?;
+    let (records, stage3) = s3.map_err(|_| PipelineError::Panicked { stage: "stage3" })?;
+    let stage2 = s2.map_err(|_| PipelineError::Panicked { stage: "stage2" })??;
+    let stage1 = s1.map_err(|_| PipelineError::Panicked { stage: "stage1" })??;
+
+    Ok(PipelineOutput {
+        dead_letters,
+        stats: PipelineStats {
+            stage1,
+            stage2,
+            stage3,
+            elapsed: started.elapsed(),
+            ..PipelineStats::default()
+        }
+        .with_latencies(&records),
+        records,
+    })
+}
+
+/// Run the pipeline with `PipelineConfig::default()`.
+pub fn run_pipeline_default() -> Result<PipelineOutput, PipelineError> {
+    run_pipeline(PipelineConfig::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::{check_completeness, validate_batch, Crc32};
+    use std::sync::mpsc;
+
+    /// Run the pipeline on another thread, failing if it takes over 5s.
+    fn run_with_timeout(config: PipelineConfig) -> PipelineOutput {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(run_pipeline(config).expect("pipeline failed"));
+        });
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("pipeline deadlocked (no result within 5s)")
+    }
+
+    #[test]
+    fn pipeline_completes_with_bounded_feedback() {
+        for channel_bound in [1, CHANNEL_BOUND] {
+            let results = run_with_timeout(PipelineConfig {
+                channel_bound,
+                ..PipelineConfig::default()
+            })
+            .records;
+
+            assert_eq!(results.len(), NUM_RECORDS as usize);
+            let (missing, duplicates) = check_completeness(&results, NUM_RECORDS);
+            assert!(missing.is_empty() && duplicates.is_empty());
+            assert_eq!(validate_batch(&results).0, results.len());
+
+            // Every id divisible by 10 went round the feedback loop once.
+            for r in &results {
+                assert_eq!(r.retry_count, u32::from(r.id % 10 == 0), "record {}", r.id);
+            }
+        }
+    }
+
+    #[test]
+    fn pipeline_with_crc32() {
+        let results = run_with_timeout(PipelineConfig {
+            num_records: 50,
+            checksum: Crc32::compute,
+            ..PipelineConfig::default()
+        })
+        .records;
+
+        assert_eq!(results.len(), 50);
+        assert_eq!(validate_batch(&results).0, 50);
+        for r in &results {
+            assert_eq!(r.checksum, Crc32::compute(r.payload.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn pipeline_with_stage2_pool() {
+        for channel_bound in [1, CHANNEL_BOUND] {
+            let results = run_with_timeout(PipelineConfig {
+                channel_bound,
+                stage2_workers: 4,
+                ..PipelineConfig::default()
+            })
+            .records;
 
-    results
+            let (missing, duplicates) = check_completeness(&results, NUM_RECORDS);
+            assert!(missing.is_empty(), "missing: {:?}", missing);
+            assert!(duplicates.is_empty(), "duplicates: {:?}", duplicates);
+            assert_eq!(validate_batch(&results).0, NUM_RECORDS as usize);
+            for r in &results {
+                assert_eq!(r.retry_count, u32::from(r.id % 10 == 0), "record {}", r.id);
+            }
+        }
+    }
+
+    #[test]
+    fn stats_account_for_every_record() {
+        for stage2_workers in [1, 4] {
+            let stats = run_with_timeout(PipelineConfig {
+                stage2_workers,
+                ..PipelineConfig::default()
+            })
+            .stats;
+
+            assert_eq!(stats.stage1.processed, NUM_RECORDS);
+            assert_eq!(stats.stage3.processed, NUM_RECORDS);
+
+            // One retry for each id divisible by 10.
+            let retries = NUM_RECORDS / 10;
+            assert_eq!(stats.stage1.feedback, retries);
+            assert_eq!(stats.stage1.forwarded, NUM_RECORDS + retries);
+            assert_eq!(stats.stage2.processed, NUM_RECORDS + retries);
+            assert_eq!(stats.stage2.forwarded, NUM_RECORDS);
+            assert_eq!(stats.stage2.feedback, retries);
+
+            assert!(stats.stage1.elapsed <= stats.elapsed);
+            assert!(stats.stage3.throughput() > 0.0);
+        }
+    }
+
+    #[test]
+    fn dead_letter_collects_exhausted_retries() {
+        for stage2_workers in [1, 4] {
+            let output = run_with_timeout(PipelineConfig {
+                stage2_workers,
+                dead_letter: true,
+                ..PipelineConfig::default()
+            });
+
+            // Every id divisible by 10 still wants a retry after its one
+            // allowed retry, so all of them end up dead-lettered.
+            assert_eq!(output.dead_letters.len(), (NUM_RECORDS / 10) as usize);
+            assert_eq!(output.stats.stage2.dead_lettered, NUM_RECORDS / 10);
+            for r in &output.dead_letters {
+                assert_eq!(r.id % 10, 0);
+                assert_eq!(r.retry_count, 1);
+                assert!(r.verify());
+            }
+            assert!(output.records.iter().all(|r| r.id % 10 != 0));
+
+            let mut all = output.records;
+            all.extend(output.dead_letters);
+            let (missing, duplicates) = check_completeness(&all, NUM_RECORDS);
+            assert!(missing.is_empty() && duplicates.is_empty());
+        }
+    }
+
+    #[test]
+    fn retry_predicate_that_never_fires() {
+        let output = run_with_timeout(PipelineConfig {
+            retry_if: Arc::new(|_| false),
+            ..PipelineConfig::default()
+        });
+
+        assert_eq!(output.records.len(), NUM_RECORDS as usize);
+        assert_eq!(output.stats.stage1.feedback, 0);
+        assert_eq!(output.stats.stage2.feedback, 0);
+        assert!(output.records.iter().all(|r| r.retry_count == 0));
+    }
+
+    #[test]
+    fn retry_predicate_selects_records() {
+        let output = run_with_timeout(PipelineConfig {
+            retry_if: Arc::new(|r| r.id <= 3),
+            ..PipelineConfig::default()
+        });
+
+        assert_eq!(output.stats.stage2.feedback, 3);
+        for r in &output.records {
+            assert_eq!(r.retry_count, u32::from(r.id <= 3), "record {}", r.id);
+        }
+    }
+
+    #[test]
+    fn records_carry_end_to_end_latency() {
+        let output = run_with_timeout(PipelineConfig::default());
+        let records = &output.records;
+
+        for r in records {
+            let latency = r.latency().expect("collected record has no latency");
+            assert!(latency > Duration::ZERO, "record {}", r.id);
+        }
+        // Results are in collection order.
+        for pair in records.windows(2) {
+            assert!(pair[0].completed_at <= pair[1].completed_at);
+        }
+        // Records are created in id order.
+        let mut by_id: Vec<&Record> = records.iter().collect();
+        by_id.sort_by_key(|r| r.id);
+        for pair in by_id.windows(2) {
+            assert!(pair[0].created_at <= pair[1].created_at);
+        }
+
+        let p50 = output.stats.latency_p50.unwrap();
+        let p99 = output.stats.latency_p99.unwrap();
+        assert!(Duration::ZERO < p50 && p50 <= p99);
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let values: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&values, 50), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&values, 99), Some(Duration::from_millis(99)));
+        assert_eq!(percentile(&values[..1], 99), Some(Duration::from_millis(1)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn ordered_output_is_sorted_by_id() {
+        let results = run_with_timeout(PipelineConfig {
+            stage2_workers: 4,
+            ordered_output: true,
+            ..PipelineConfig::default()
+        })
+        .records;
+
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, (1..=NUM_RECORDS).collect::<Vec<_>>());
+        assert_eq!(validate_batch(&results).0, results.len());
+    }
+
+    #[test]
+    fn shutdown_returns_partial_results() {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&shutdown);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        // Far more records than can get through before the flag flips.
+        let results = run_with_timeout(PipelineConfig {
+            num_records: u32::MAX,
+            shutdown: Some(shutdown),
+            ..PipelineConfig::default()
+        })
+        .records;
+
+        assert!(!results.is_empty());
+        let max_id = results.iter().map(|r| r.id).max().unwrap();
+        assert!(max_id < u32::MAX);
+        // Records that weren't retried stay in order, so they form a
+        // prefix; only a retried record can be missing behind `max_id`.
+        let (missing, duplicates) = check_completeness(&results, max_id);
+        assert!(missing.iter().all(|id| id % 10 == 0), "missing: {:?}", missing);
+        assert!(duplicates.is_empty());
+        assert_eq!(validate_batch(&results).0, results.len());
+    }
+
+    #[test]
+    fn zero_channel_bound_is_rejected() {
+        let result = run_pipeline(PipelineConfig {
+            num_records: 10,
+            channel_bound: 0,
+            ..PipelineConfig::default()
+        });
+        assert_eq!(result.err(), Some(PipelineError::ZeroChannelBound));
+    }
+
+    #[test]
+    fn zero_workers_is_rejected() {
+        let result = run_pipeline(PipelineConfig {
+            num_records: 10,
+            stage2_workers: 0,
+            ..PipelineConfig::default()
+        });
+        assert_eq!(result.err(), Some(PipelineError::ZeroWorkers));
+    }
 }
@@ -1,114 +1,517 @@
-use std::sync::mpsc::{Receiver, SyncSender};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{
+    Receiver, RecvError, RecvTimeoutError, SyncSender, TryRecvError, TrySendError,
+};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use crate::error::PipelineError;
 use crate::worker::{do_work, Record};
 
 /// The maximum pipeline stage at which records can still be retried.
 /// Once a record's stage reaches this value, it is forwarded to the
-/// next stage regardless of its retry eligibility.
+/// next stage regardless of its retry eligibility, or dead-lettered if
+/// Stage 2 has a dead-letter channel.
 const MAX_RETRY_STAGE: u32 = 4;
 
+/// Decides which records Stage 2 sends back to Stage 1 for a retry.
+pub type RetryPredicate = Arc<dyn Fn(&Record) -> bool + Send + Sync>;
+
+/// The benchmark's retry trigger: every tenth record needs a retry.
+pub fn retry_every_tenth(record: &Record) -> bool {
+    record.id % 10 == 0
+}
+
+/// How long Stage 1 waits on one channel before checking the others.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Counters and timing a stage reports when it finishes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageStats {
+    /// Records taken from the stage's input channel (not counting
+    /// feedback).
+    pub processed: u32,
+    /// Records sent on to the next stage.
+    pub forwarded: u32,
+    /// Records sent back (Stage 2) or reprocessed (Stage 1) via feedback.
+    pub feedback: u32,
+    /// Records Stage 2 sent to the dead-letter channel.
+    pub dead_lettered: u32,
+    /// Wall-clock time from the stage starting to it finishing.
+    pub elapsed: Duration,
+}
+
+impl StageStats {
+    /// Records per second over `elapsed`, or 0 if no time was measured.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.processed as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Add `other`'s counters; `elapsed` is left alone.
+    pub(crate) fn add(&mut self, other: StageStats) {
+        self.processed += other.processed;
+        self.forwarded += other.forwarded;
+        self.feedback += other.feedback;
+        self.dead_lettered += other.dead_lettered;
+    }
+}
+
 /// Stage 1: Ingestion.
 ///
 /// Reads raw records from `input`, processes them, and forwards to
 /// Stage 2 via `output`.  Also listens for feedback from Stage 2 on
 /// `feedback_rx` and re-processes those records.
+///
+/// Stage 1 never blocks on a send.  Feedback is drained on every pass and
+/// records that don't fit in `output` wait in a local queue.  Otherwise
+/// Stage 1 could sit on a full `output` while Stage 2 sits on a full
+/// feedback channel, each waiting for the other.
+///
+/// `in_flight` counts records sent to Stage 2 that it has not finished
+/// with; Stage 2 decrements it only after any feedback send.  Once input
+/// is closed and nothing is queued or in flight, no more feedback can
+/// arrive and Stage 1 exits.  (Waiting for the feedback channel to close
+/// instead can never end: Stage 2 only closes it after Stage 1 exits.)
+///
+/// Once `shutdown` is set, Stage 1 stops at the top of its next pass,
+/// dropping any records still queued locally.
+///
+/// Returns `PipelineError::Disconnected` if Stage 2 goes away first,
+/// unless the pipeline is shutting down.
 pub fn stage1(
     input: Receiver<Record>,
     output: SyncSender<Record>,
     feedback_rx: Receiver<Record>,
-) {
-    let mut records_sent = 0u32;
-    let mut feedback_processed = 0u32;
-
-    // --- Primary loop: drain all input records ---
-    for mut record in input {
-        do_work(&mut record, "stage1");
-        output.send(record).expect("stage1 -> stage2 send failed");
-        records_sent += 1;
-    }
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<StageStats, PipelineError> {
+    let started = Instant::now();
+    let disconnected = PipelineError::Disconnected { stage: "stage1" };
+    let mut stats = StageStats::default();
+    let mut pending: VecDeque<Record> = VecDeque::new();
+    let mut input_open = true;
+
+    'run: loop {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // Read this before draining feedback: Stage 2 queues feedback
+        // before releasing the record, so if nothing is in flight now,
+        // every feedback record is already in the channel.
+        let settled =
+            !input_open && pending.is_empty() && in_flight.load(Ordering::SeqCst) == 0;
+        let mut progressed = false;
+
+        // --- Reprocess records that Stage 2 sent back ---
+        loop {
+            match feedback_rx.try_recv() {
+                Ok(mut record) => {
+                    record.mark_retry();
+                    do_work(&mut record, "stage1-redo");
+                    pending.push_back(record);
+                    stats.feedback += 1;
+                    progressed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                // Stage 2 holds the feedback sender until Stage 1 closes
+                // `output`, so losing it now means Stage 2 is gone.
+                Err(TryRecvError::Disconnected) if shutdown.load(Ordering::SeqCst) => {
+                    break 'run;
+                }
+                Err(TryRecvError::Disconnected) => return Err(disconnected),
+            }
+        }
 
-    // --- Feedback loop: reprocess records that Stage 2 sent back ---
-    for mut record in feedback_rx {
-        record.mark_retry();
-        do_work(&mut record, "stage1-redo");
-        output.send(record).expect("stage1 -> stage2 redo send failed");
-        feedback_processed += 1;
+        if settled && !progressed {
+            break;
+        }
+
+        // --- Take new input only when nothing is waiting to go out ---
+        if pending.is_empty() && input_open {
+            match input.recv_timeout(POLL_INTERVAL) {
+                Ok(mut record) => {
+                    do_work(&mut record, "stage1");
+                    pending.push_back(record);
+                    stats.processed += 1;
+                    progressed = true;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => input_open = false,
+            }
+        }
+
+        // --- Forward as much as Stage 2 will take right now ---
+        while let Some(record) = pending.pop_front() {
+            in_flight.fetch_add(1, Ordering::SeqCst);
+            match output.try_send(record) {
+                Ok(()) => {
+                    stats.forwarded += 1;
+                    progressed = true;
+                }
+                Err(TrySendError::Full(record)) => {
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    pending.push_front(record);
+                    break;
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    if shutdown.load(Ordering::SeqCst) {
+                        break 'run;
+                    }
+                    return Err(disconnected);
+                }
+            }
+        }
+
+        if !progressed {
+            thread::sleep(POLL_INTERVAL);
+        }
     }
 
     // Drop the output sender to signal downstream that Stage 1 is done.
     drop(output);
+    stats.elapsed = started.elapsed();
 
     eprintln!(
-        "[stage1] finished: sent={}, feedback={}",
-        records_sent, feedback_processed
+        "[stage1] finished: processed={}, forwarded={}, feedback={}",
+        stats.processed, stats.forwarded, stats.feedback
     );
+    Ok(stats)
 }
 
 /// Stage 2: Transformation.
 ///
 /// Reads from Stage 1, transforms records, and forwards to Stage 3.
-/// Records whose id is divisible by 10 are sent back to Stage 1 for
-/// reprocessing via `feedback_tx`, simulating a "needs retry" signal.
+/// Records matching `retry_if` are sent back to Stage 1 for reprocessing
+/// via `feedback_tx`.
 ///
 /// The retry only happens while the record's `stage` is below
-/// `MAX_RETRY_STAGE`, preventing infinite loops.
+/// `MAX_RETRY_STAGE`, preventing infinite loops.  A record that still
+/// needs a retry past that point goes to `dead_letter` if one is given,
+/// and on to Stage 3 otherwise.
+///
+/// Each record is released from `in_flight` once it has been sent on.
+/// Once `shutdown` is set, Stage 2 finishes the record in hand and exits.
+///
+/// Returns `PipelineError::Disconnected` if Stage 1, Stage 3 or the
+/// dead-letter receiver goes away first, unless the pipeline is shutting
+/// down.
 pub fn stage2(
     input: Receiver<Record>,
     output: SyncSender<Record>,
     feedback_tx: SyncSender<Record>,
-) {
-    let mut forwarded = 0u32;
-    let mut feedback_sent = 0u32;
+    dead_letter: Option<SyncSender<Record>>,
+    retry_if: RetryPredicate,
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+) -> Result<StageStats, PipelineError> {
+    let started = Instant::now();
+    let mut stats = stage2_loop(
+        || input.recv(),
+        &output,
+        &feedback_tx,
+        dead_letter.as_ref(),
+        &*retry_if,
+        &in_flight,
+        &shutdown,
+    )?;
+
+    drop(feedback_tx);
+    drop(output);
+    stats.elapsed = started.elapsed();
 
-    for mut record in input {
-        do_work(&mut record, "stage2");
+    eprintln!(
+        "[stage2] finished: processed={}, forwarded={}, feedback={}, dead_lettered={}",
+        stats.processed, stats.forwarded, stats.feedback, stats.dead_lettered
+    );
+    Ok(stats)
+}
 
-        let needs_retry = record.id % 10 == 0 && record.stage < MAX_RETRY_STAGE;
+/// Stage 2 spread over `workers` threads.
+///
+/// Each worker pulls from the shared `input` and behaves exactly like
+/// `stage2`, including the retry feedback and `in_flight` accounting.
+/// Records reach Stage 3 in whatever order the workers finish them, so
+/// output order is not preserved even for records that were not retried.
+///
+/// Returns the first error any worker hit.  Panics if `workers` is 0.
+#[allow(clippy::too_many_arguments)]
+pub fn stage2_pool(
+    input: Receiver<Record>,
+    output: SyncSender<Record>,
+    feedback_tx: SyncSender<Record>,
+    dead_letter: Option<SyncSender<Record>>,
+    retry_if: RetryPredicate,
+    in_flight: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+    workers: usize,
+) -> Result<StageStats, PipelineError> {
+    assert!(workers >= 1, "stage2_pool needs at least one worker");
+    let started = Instant::now();
+    let input = Arc::new(Mutex::new(input));
 
-        if needs_retry {
-            feedback_tx
-                .send(record)
-                .expect("stage2 -> stage1 feedback send failed");
-            feedback_sent += 1;
-        } else {
-            output.send(record).expect("stage2 -> stage3 send failed");
-            forwarded += 1;
+    let handles: Vec<_> = (0..workers)
+        .map(|i| {
+            let input = Arc::clone(&input);
+            let output = output.clone();
+            let feedback_tx = feedback_tx.clone();
+            let dead_letter = dead_letter.clone();
+            let retry_if = Arc::clone(&retry_if);
+            let in_flight = Arc::clone(&in_flight);
+            let shutdown = Arc::clone(&shutdown);
+            thread::Builder::new()
+                .name(format!("stage-2-{}", i))
+                .spawn(move || {
+                    // Hold the lock only for the receive, not the work.
+                    let recv = || input.lock().expect("stage2 input lock poisoned").recv();
+                    stage2_loop(
+                        recv,
+                        &output,
+                        &feedback_tx,
+                        dead_letter.as_ref(),
+                        &*retry_if,
+                        &in_flight,
+                        &shutdown,
+                    )
+                })
+                .expect("failed to spawn stage 2 worker")
+        })
+        .collect();
+
+    let mut stats = StageStats::default();
+    let mut first_error = None;
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(worker_stats)) => stats.add(worker_stats),
+            Ok(Err(e)) => {
+                first_error.get_or_insert(e);
+            }
+            Err(_) => {
+                first_error.get_or_insert(PipelineError::Panicked { stage: "stage2" });
+            }
         }
     }
 
     drop(feedback_tx);
+    drop(dead_letter);
     drop(output);
 
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    stats.elapsed = started.elapsed();
+
     eprintln!(
-        "[stage2] finished: forwarded={}, feedback={}",
-        forwarded, feedback_sent
+        "[stage2] finished: processed={}, forwarded={}, feedback={}, dead_lettered={}, workers={}",
+        stats.processed, stats.forwarded, stats.feedback, stats.dead_lettered, workers
     );
+    Ok(stats)
+}
+
+/// Run Stage 2 over records from `recv` until it fails, `shutdown` is
+/// set, or Stage 1 closes the channel.
+fn stage2_loop(
+    mut recv: impl FnMut() -> Result<Record, RecvError>,
+    output: &SyncSender<Record>,
+    feedback_tx: &SyncSender<Record>,
+    dead_letter: Option<&SyncSender<Record>>,
+    retry_if: &(dyn Fn(&Record) -> bool + Send + Sync),
+    in_flight: &AtomicUsize,
+    shutdown: &AtomicBool,
+) -> Result<StageStats, PipelineError> {
+    let mut stats = StageStats::default();
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let Ok(record) = recv() else { break };
+        stats.processed += 1;
+        match stage2_step(record, output, feedback_tx, dead_letter, retry_if, in_flight) {
+            Ok(Route::Output) => stats.forwarded += 1,
+            Ok(Route::Feedback) => stats.feedback += 1,
+            Ok(Route::DeadLetter) => stats.dead_lettered += 1,
+            // Stage 1 leaves without waiting for us when shutting down.
+            Err(_) if shutdown.load(Ordering::SeqCst) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(stats)
+}
+
+/// Where Stage 2 sends a record.
+pub(crate) enum Route {
+    Output,
+    Feedback,
+    DeadLetter,
+}
+
+/// Decide where Stage 2 sends `record` once it has been transformed.
+pub(crate) fn route(record: &Record, needs_retry: bool, has_dead_letter: bool) -> Route {
+    if needs_retry && record.stage < MAX_RETRY_STAGE {
+        Route::Feedback
+    } else if needs_retry && has_dead_letter {
+        Route::DeadLetter
+    } else {
+        Route::Output
+    }
+}
+
+/// Transform one record and send it on, returning where it went.
+fn stage2_step(
+    mut record: Record,
+    output: &SyncSender<Record>,
+    feedback_tx: &SyncSender<Record>,
+    dead_letter: Option<&SyncSender<Record>>,
+    retry_if: &(dyn Fn(&Record) -> bool + Send + Sync),
+    in_flight: &AtomicUsize,
+) -> Result<Route, PipelineError> {
+    do_work(&mut record, "stage2");
+
+    let route = route(&record, retry_if(&record), dead_letter.is_some());
+    let sent = match (&route, dead_letter) {
+        (Route::Feedback, _) => feedback_tx.send(record).is_ok(),
+        (Route::DeadLetter, Some(dead_letter)) => dead_letter.send(record).is_ok(),
+        _ => output.send(record).is_ok(),
+    };
+    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+    if sent {
+        Ok(route)
+    } else {
+        Err(PipelineError::Disconnected { stage: "stage2" })
+    }
 }
 
 /// Stage 3: Output / collection.
 ///
-/// Collects all processed records into a vector.  Also performs a basic
+/// Collects all processed records into a vector, until Stage 2 closes its
+/// channel (which it also does on shutdown).  Also performs a basic
 /// integrity check on each record as it arrives.
-pub fn stage3(input: Receiver<Record>) -> Vec<Record> {
-    let mut results = Vec::new();
-    let mut integrity_errors = 0u32;
-
+///
+/// Records are returned in arrival order, or sorted by id if `ordered`
+/// is set.  When ordering, a record seen twice under the same id keeps
+/// only its last version.  The returned stats count every record
+/// received, including any such duplicates.
+pub fn stage3(input: Receiver<Record>, ordered: bool) -> (Vec<Record>, StageStats) {
+    let mut collector = Collector::new(ordered);
     for record in input {
+        collector.push(record);
+    }
+    collector.finish()
+}
+
+/// Stage 3's bookkeeping, shared with the async pipeline.
+pub(crate) struct Collector {
+    ordered: bool,
+    started: Instant,
+    stats: StageStats,
+    results: Vec<Record>,
+    by_id: BTreeMap<u32, Record>,
+    integrity_errors: u32,
+}
+
+impl Collector {
+    pub(crate) fn new(ordered: bool) -> Self {
+        Collector {
+            ordered,
+            started: Instant::now(),
+            stats: StageStats::default(),
+            results: Vec::new(),
+            by_id: BTreeMap::new(),
+            integrity_errors: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, mut record: Record) {
+        record.completed_at = Some(Instant::now());
+        self.stats.processed += 1;
         if !record.verify() {
-            integrity_errors += 1;
+            self.integrity_errors += 1;
             eprintln!(
                 "[stage3] integrity error on record {}: checksum mismatch",
                 record.id
             );
         }
-        results.push(record);
+        if self.ordered {
+            self.by_id.insert(record.id, record);
+        } else {
+            self.results.push(record);
+        }
     }
 
-    eprintln!(
-        "[stage3] finished: collected={}, integrity_errors={}",
-        results.len(),
-        integrity_errors
-    );
+    pub(crate) fn finish(mut self) -> (Vec<Record>, StageStats) {
+        if self.ordered {
+            self.results = self.by_id.into_values().collect();
+        }
+
+        eprintln!(
+            "[stage3] finished: collected={}, integrity_errors={}",
+            self.results.len(),
+            self.integrity_errors
+        );
+
+        self.stats.elapsed = self.started.elapsed();
+        (self.results, self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::sync_channel;
 
-    results
+    #[test]
+    fn stage1_reports_vanished_stage2() {
+        let (input_tx, input_rx) = sync_channel(1);
+        let (output_tx, output_rx) = sync_channel(1);
+        let (_feedback_tx, feedback_rx) = sync_channel(1);
+        drop(output_rx);
+
+        input_tx.send(Record::new(1)).unwrap();
+        drop(input_tx);
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let result = stage1(input_rx, output_tx, feedback_rx, Arc::clone(&in_flight), shutdown);
+        assert_eq!(result, Err(PipelineError::Disconnected { stage: "stage1" }));
+        assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn stage2_reports_vanished_stage3() {
+        for workers in [1, 3] {
+            let (input_tx, input_rx) = sync_channel(1);
+            let (output_tx, output_rx) = sync_channel(1);
+            let (feedback_tx, _feedback_rx) = sync_channel(1);
+            drop(output_rx);
+
+            input_tx.send(Record::new(1)).unwrap();
+            drop(input_tx);
+
+            let in_flight = Arc::new(AtomicUsize::new(1));
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let retry_if: RetryPredicate = Arc::new(retry_every_tenth);
+            let result = if workers == 1 {
+                stage2(input_rx, output_tx, feedback_tx, None, retry_if, in_flight, shutdown)
+            } else {
+                stage2_pool(
+                    input_rx,
+                    output_tx,
+                    feedback_tx,
+                    None,
+                    retry_if,
+                    in_flight,
+                    shutdown,
+                    workers,
+                )
+            };
+            assert_eq!(result, Err(PipelineError::Disconnected { stage: "stage2" }));
+        }
+    }
 }
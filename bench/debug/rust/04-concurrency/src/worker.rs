@@ -1,26 +1,110 @@
+use std::time::{Duration, Instant};
+
+/// A checksum algorithm over raw bytes.
+pub trait Checksum {
+    fn compute(data: &[u8]) -> u32;
+}
+
+/// A checksum function as stored on a `Record`, e.g. `Crc32::compute`.
+pub type ChecksumFn = fn(&[u8]) -> u32;
+
+/// Sum of all bytes mod 2^32.  Cheap, but blind to reordering.
+pub struct ByteSum;
+
+impl Checksum for ByteSum {
+    fn compute(data: &[u8]) -> u32 {
+        data.iter().fold(0u32, |acc, &b| acc.wrapping_add(b as u32))
+    }
+}
+
+/// CRC-32 (IEEE 802.3, reflected polynomial `0xEDB88320`).
+#[allow(dead_code)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn compute(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &b in data {
+            crc ^= b as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+        !crc
+    }
+}
+
+/// A payload whose integrity a `Record` can check.
+pub trait Checksummable {
+    /// The bytes the checksum is computed over.
+    fn checksum_bytes(&self) -> &[u8];
+}
+
+impl Checksummable for String {
+    fn checksum_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Checksummable for Vec<u8> {
+    fn checksum_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
 /// A processed record flowing through the pipeline.
+///
+/// The payload type defaults to `String`, which is what the pipeline
+/// stages use, so plain `Record` means `Record<String>`.
 #[derive(Debug, Clone)]
-pub struct Record {
+pub struct Record<T = String> {
     pub id: u32,
-    pub payload: String,
+    pub payload: T,
     pub stage: u32,
     pub checksum: u32,
     pub retry_count: u32,
+    /// When the record was created.
+    pub created_at: Instant,
+    /// When Stage 3 collected the record, if it has.
+    pub completed_at: Option<Instant>,
+    checksum_fn: ChecksumFn,
 }
 
 impl Record {
     pub fn new(id: u32) -> Self {
-        let payload = format!("data-{:04}", id);
-        let checksum = compute_checksum(&payload);
+        Record::with_payload(id, format!("data-{:04}", id))
+    }
+}
+
+impl<T: Clone + Checksummable> Record<T> {
+    /// Create a record at stage 0 carrying `payload`, checksummed with
+    /// `ByteSum`.
+    pub fn with_payload(id: u32, payload: T) -> Self {
+        let checksum = ByteSum::compute(payload.checksum_bytes());
         Record {
             id,
             payload,
             stage: 0,
             checksum,
             retry_count: 0,
+            created_at: Instant::now(),
+            completed_at: None,
+            checksum_fn: ByteSum::compute,
         }
     }
 
+    /// Switch the record to `checksum_fn` and recompute its checksum.
+    pub fn with_checksum(mut self, checksum_fn: ChecksumFn) -> Self {
+        self.checksum_fn = checksum_fn;
+        self.update_checksum();
+        self
+    }
+
+    fn update_checksum(&mut self) {
+        self.checksum = (self.checksum_fn)(self.payload.checksum_bytes());
+    }
+
     /// Advance the record to the next pipeline stage.
     pub fn advance(&mut self) {
         self.stage += 1;
@@ -31,31 +115,41 @@ impl Record {
         self.retry_count += 1;
     }
 
+    /// Time from creation to collection by Stage 3, including any trips
+    /// round the feedback loop, or `None` if not yet collected.
+    pub fn latency(&self) -> Option<Duration> {
+        self.completed_at.map(|done| done - self.created_at)
+    }
+
     /// Verify that the checksum still matches the payload.
     pub fn verify(&self) -> bool {
-        compute_checksum(&self.payload) == self.checksum
+        (self.checksum_fn)(self.payload.checksum_bytes()) == self.checksum
     }
 }
 
-/// Simple checksum: sum of all bytes mod 2^32.
-fn compute_checksum(data: &str) -> u32 {
-    data.bytes().fold(0u32, |acc, b| acc.wrapping_add(b as u32))
+/// Replace the record's payload with `transform(record)`, update the
+/// checksum, and advance the record to the next stage.
+pub fn do_work_with<T, F>(record: &mut Record<T>, transform: F)
+where
+    T: Clone + Checksummable,
+    F: FnOnce(&Record<T>) -> T,
+{
+    record.payload = transform(record);
+    record.update_checksum();
+    record.advance();
 }
 
 /// Simulate a small amount of CPU work by transforming the record's
 /// payload and updating the checksum.
 pub fn do_work(record: &mut Record, stage_name: &str) {
-    record.payload = format!(
-        "{} [{}:s{}:r{}]",
-        record.payload, stage_name, record.stage, record.retry_count
-    );
-    record.checksum = compute_checksum(&record.payload);
-    record.advance();
+    do_work_with(record, |r| {
+        format!("{} [{}:s{}:r{}]", r.payload, stage_name, r.stage, r.retry_count)
+    });
 }
 
 /// Validate a batch of output records, returning the number of valid
 /// records and a list of any that failed validation.
-pub fn validate_batch(records: &[Record]) -> (usize, Vec<u32>) {
+pub fn validate_batch<T: Clone + Checksummable>(records: &[Record<T>]) -> (usize, Vec<u32>) {
     let mut valid = 0;
     let mut invalid_ids = Vec::new();
 
@@ -72,7 +166,7 @@ pub fn validate_batch(records: &[Record]) -> (usize, Vec<u32>) {
 
 /// Check that every record id from 1..=expected_count appears
 /// exactly once in the output (no duplicates, no missing).
-pub fn check_completeness(records: &[Record], expected_count: u32) -> (Vec<u32>, Vec<u32>) {
+pub fn check_completeness<T>(records: &[Record<T>], expected_count: u32) -> (Vec<u32>, Vec<u32>) {
     let mut seen = vec![0u32; expected_count as usize + 1];
     for record in records {
         if record.id as usize <= expected_count as usize {
@@ -93,3 +187,57 @@ pub fn check_completeness(records: &[Record], expected_count: u32) -> (Vec<u32>,
 
     (missing, duplicates)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_records_keep_their_format() {
+        let mut record = Record::new(7);
+        assert_eq!(record.payload, "data-0007");
+
+        do_work(&mut record, "stage1");
+        assert_eq!(record.payload, "data-0007 [stage1:s0:r0]");
+        assert_eq!(record.stage, 1);
+        assert!(record.verify());
+    }
+
+    #[test]
+    fn binary_payloads() {
+        let mut record: Record<Vec<u8>> = Record::with_payload(1, vec![1, 2, 3]);
+        assert_eq!(record.checksum, 6);
+
+        do_work_with(&mut record, |r| {
+            let mut next = r.payload.clone();
+            next.push(r.stage as u8 + 10);
+            next
+        });
+        assert_eq!(record.payload, vec![1, 2, 3, 10]);
+        assert_eq!(record.checksum, 16);
+        assert!(record.verify());
+
+        record.payload[0] = 9;
+        assert!(!record.verify());
+        assert_eq!(validate_batch(&[record]), (0, vec![1]));
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(Crc32::compute(b"123456789"), 0xCBF4_3926);
+        assert_eq!(Crc32::compute(b""), 0);
+    }
+
+    #[test]
+    fn crc32_catches_transpositions_bytesum_misses() {
+        assert_eq!(ByteSum::compute(b"data-0012"), ByteSum::compute(b"data-0021"));
+        assert_ne!(Crc32::compute(b"data-0012"), Crc32::compute(b"data-0021"));
+
+        let swap = |mut r: Record| {
+            r.payload = r.payload.replace("0012", "0021");
+            r
+        };
+        assert!(swap(Record::new(12)).verify());
+        assert!(!swap(Record::new(12).with_checksum(Crc32::compute)).verify());
+    }
+}
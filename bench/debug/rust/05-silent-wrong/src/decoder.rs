@@ -1,4 +1,10 @@
-use crate::types::{DecodeResult, CONTINUATION_BIT, DATA_BITS, DATA_MASK, MAX_VARINT_BYTES};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::types::{
+    zigzag_decode, DecodeError, DecodeResult, VarintInt, CONTINUATION_BIT, DATA_BITS, DATA_MASK,
+    MAX_VARINT_BYTES,
+};
 
 /// Decode a varint from the front of `bytes`.
 ///
@@ -10,34 +16,259 @@ use crate::types::{DecodeResult, CONTINUATION_BIT, DATA_BITS, DATA_MASK, MAX_VAR
 ///
 /// To reconstruct the value, each byte's data bits are shifted left
 /// by `shift` and OR-ed into the accumulator.
+///
+/// Malformed input is not reported: truncated and overlong sequences
+/// yield whatever was accumulated, and bits past 64 are dropped.  Use
+/// [`try_decode_varint`] to detect corruption.
 pub fn decode_varint(bytes: &[u8]) -> DecodeResult {
+    scan_varint(bytes).0
+}
+
+/// Decode a varint from the front of `bytes`, rejecting malformed input.
+///
+/// Fails with `Truncated` if `bytes` ends (or is empty) while a varint
+/// is still in progress, `Overlong` if no byte within `MAX_VARINT_BYTES`
+/// ends it, and `Overflow` if the value needs more than 64 bits.
+#[allow(dead_code)]
+pub fn try_decode_varint(bytes: &[u8]) -> Result<DecodeResult, DecodeError> {
+    match scan_varint(bytes) {
+        (result, None) => Ok(result),
+        (_, Some(err)) => Err(err),
+    }
+}
+
+/// Like [`try_decode_varint`], but also rejects padded encodings such as
+/// `[0x80, 0x00]` for 0 with `NonCanonical`, so every value has exactly
+/// one accepted byte sequence.
+#[allow(dead_code)]
+pub fn decode_varint_canonical(bytes: &[u8]) -> Result<DecodeResult, DecodeError> {
+    let result = try_decode_varint(bytes)?;
+    if result.bytes_read > 1 && bytes[result.bytes_read - 1] == 0x00 {
+        return Err(DecodeError::NonCanonical);
+    }
+    Ok(result)
+}
+
+/// Like [`try_decode_varint`], but fails with `ExceedsMax` if the value
+/// is greater than `max`.
+///
+/// Stops as soon as the bits read so far already exceed `max`, so an
+/// oversized length field is rejected without reading the rest of it,
+/// even if the rest is truncated or overlong.
+#[allow(dead_code)]
+pub fn decode_varint_max(bytes: &[u8], max: u64) -> Result<DecodeResult, DecodeError> {
     let mut result: u64 = 0;
     let mut shift: u32 = 0;
 
     for (i, &byte) in bytes.iter().enumerate() {
         if i >= MAX_VARINT_BYTES {
+            return Err(DecodeError::Overlong);
+        }
+
+        let (next, fits) = result.with_group(byte & DATA_MASK, shift);
+        if !fits {
+            return Err(DecodeError::Overflow);
+        }
+        result = next;
+
+        // Later bytes only add higher bits, so this can only grow.
+        if result > max {
+            return Err(DecodeError::ExceedsMax);
+        }
+
+        shift += DATA_BITS;
+
+        if byte & CONTINUATION_BIT == 0 {
+            return Ok(DecodeResult::new(result, i + 1));
+        }
+    }
+
+    Err(DecodeError::Truncated)
+}
+
+/// Decode a `u32` varint, returning `(value, bytes_read)`.  Values above
+/// `u32::MAX` fail with `Overflow`; more than `MAX_VARINT_BYTES_32` bytes
+/// with `Overlong`.
+#[allow(dead_code)]
+pub fn decode_varint_u32(bytes: &[u8]) -> Result<(u32, usize), DecodeError> {
+    checked(scan(bytes))
+}
+
+/// Decode a `u128` varint, returning `(value, bytes_read)`.  Accepts up
+/// to `MAX_VARINT_BYTES_128` bytes.
+#[allow(dead_code)]
+pub fn decode_varint_u128(bytes: &[u8]) -> Result<(u128, usize), DecodeError> {
+    checked(scan(bytes))
+}
+
+/// Shared decode loop: the best-effort result `decode_varint` has always
+/// returned, plus the first problem found, if any.
+fn scan_varint(bytes: &[u8]) -> (DecodeResult, Option<DecodeError>) {
+    let (value, bytes_read, error) = scan::<u64>(bytes);
+    (DecodeResult::new(value, bytes_read), error)
+}
+
+/// Turn a `scan` result into `Ok((value, bytes_read))` or its error.
+fn checked<T>(
+    (value, bytes_read, error): (T, usize, Option<DecodeError>),
+) -> Result<(T, usize), DecodeError> {
+    match error {
+        None => Ok((value, bytes_read)),
+        Some(err) => Err(err),
+    }
+}
+
+/// The decode loop for any width: `(value, bytes_read, first_error)`.
+fn scan<T: VarintInt>(bytes: &[u8]) -> (T, usize, Option<DecodeError>) {
+    let mut result = T::ZERO;
+    let mut shift: u32 = 0;
+    let mut error = None;
+
+    for (i, &byte) in bytes.iter().enumerate() {
+        if i >= T::MAX_BYTES {
             // Overlong encoding -- truncate to prevent infinite reads.
-            return DecodeResult::new(result, i);
+            return (result, i, Some(DecodeError::Overlong));
         }
 
-        let data = (byte & DATA_MASK) as u64;
-        result |= data << shift;
+        let (next, fits) = result.with_group(byte & DATA_MASK, shift);
+        if !fits {
+            // Bits were shifted out of the top of the value.
+            error.get_or_insert(DecodeError::Overflow);
+        }
+        result = next;
 
         shift += DATA_BITS;
 
         // Stop when the continuation bit is clear.
         if byte & CONTINUATION_BIT == 0 {
-            return DecodeResult::new(result, i + 1);
+            return (result, i + 1, error);
         }
     }
 
     // Reached end of buffer while continuation bit was still set.
-    DecodeResult::new(result, bytes.len())
+    (result, bytes.len(), Some(error.unwrap_or(DecodeError::Truncated)))
+}
+
+/// Incremental decoder for varints that may span buffer boundaries.
+///
+/// Feed bytes one at a time with [`push`](VarintDecoder::push); it returns
+/// `Some(value)` on the byte that ends a varint and is then ready for the
+/// next one.  If more than `MAX_VARINT_BYTES` bytes arrive without an
+/// end, the decoder becomes [overlong](VarintDecoder::is_overlong) and
+/// ignores input until [`reset`](VarintDecoder::reset).
+#[derive(Debug, Default, Clone)]
+#[allow(dead_code)]
+pub struct VarintDecoder {
+    result: u64,
+    shift: u32,
+    bytes_read: usize,
+    overlong: bool,
+}
+
+#[allow(dead_code)]
+impl VarintDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume one byte.  Returns the decoded value if `byte` completes a
+    /// varint, `None` if more bytes are needed or the decoder is overlong.
+    pub fn push(&mut self, byte: u8) -> Option<u64> {
+        if self.overlong {
+            return None;
+        }
+        if self.bytes_read >= MAX_VARINT_BYTES {
+            self.overlong = true;
+            return None;
+        }
+
+        self.result |= ((byte & DATA_MASK) as u64) << self.shift;
+        self.shift += DATA_BITS;
+        self.bytes_read += 1;
+
+        if byte & CONTINUATION_BIT == 0 {
+            let value = self.result;
+            self.reset();
+            return Some(value);
+        }
+        None
+    }
+
+    /// Bytes consumed by the varint currently in progress.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Whether a varint has been started but not finished.
+    pub fn in_progress(&self) -> bool {
+        self.bytes_read > 0
+    }
+
+    /// Whether the current sequence ran past `MAX_VARINT_BYTES`.
+    pub fn is_overlong(&self) -> bool {
+        self.overlong
+    }
+
+    /// Discard any partial varint and clear the overlong state.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Read one varint from `r`, a byte at a time, without buffering ahead.
+///
+/// Errors:
+/// - `UnexpectedEof` if the stream ends before the first byte (a clean
+///   end of stream between values);
+/// - `InvalidData` if it ends in the middle of a varint, or if more than
+///   `MAX_VARINT_BYTES` bytes carry the continuation flag;
+/// - any other error from `r`.
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+pub fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut result: u64 = 0;
+    let mut shift: u32 = 0;
+    let mut byte = [0u8; 1];
+
+    for i in 0..MAX_VARINT_BYTES {
+        if let Err(e) = r.read_exact(&mut byte) {
+            if e.kind() == io::ErrorKind::UnexpectedEof && i > 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("truncated varint: stream ended after {} bytes", i),
+                ));
+            }
+            return Err(e);
+        }
+
+        result |= ((byte[0] & DATA_MASK) as u64) << shift;
+        shift += DATA_BITS;
+
+        if byte[0] & CONTINUATION_BIT == 0 {
+            return Ok(result);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("varint longer than {} bytes", MAX_VARINT_BYTES),
+    ))
+}
+
+/// Decode a zigzag varint written by `encode_svarint`.
+///
+/// Returns `(value, bytes_read)`.
+#[allow(dead_code)]
+pub fn decode_svarint(bytes: &[u8]) -> (i64, usize) {
+    let result = decode_varint(bytes);
+    (zigzag_decode(result.value), result.bytes_read)
 }
 
 /// Decode a sequence of concatenated varints from a byte stream.
 ///
 /// Returns a vector of decoded values.  Stops when all bytes are consumed.
+#[allow(dead_code)]
+#[cfg(feature = "alloc")]
 pub fn decode_many(bytes: &[u8]) -> Vec<u64> {
     let mut values = Vec::new();
     let mut offset = 0;
@@ -54,15 +285,79 @@ pub fn decode_many(bytes: &[u8]) -> Vec<u64> {
     values
 }
 
+/// Lazily decodes concatenated varints from a byte slice.
+///
+/// Yields `Ok(value)` per varint and ends at the end of the buffer.  A
+/// malformed varint (e.g. truncated at the end) is yielded once as `Err`,
+/// after which the iterator is exhausted.
+#[allow(dead_code)]
+pub struct VarintIter<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> VarintIter<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        VarintIter { bytes, offset: 0 }
+    }
+
+    /// Bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Iterator for VarintIter<'_> {
+    type Item = Result<u64, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.bytes.len() {
+            return None;
+        }
+        match try_decode_varint(&self.bytes[self.offset..]) {
+            Ok(result) => {
+                self.offset += result.bytes_read;
+                Some(Ok(result.value))
+            }
+            Err(err) => {
+                self.offset = self.bytes.len();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
 /// Decode a single varint from `bytes` starting at `offset`.
 ///
 /// Returns `(value, new_offset)`.  Useful for parsing a stream of varints
 /// mixed with other data.
+#[allow(dead_code)]
 pub fn decode_varint_at(bytes: &[u8], offset: usize) -> (u64, usize) {
     let result = decode_varint(&bytes[offset..]);
     (result.value, offset + result.bytes_read)
 }
 
+/// Return the offset just past the varint starting at `offset`, without
+/// decoding it.
+///
+/// Only looks for the first byte with the continuation bit clear.  Fails
+/// with `Truncated` if the input ends first (or `offset` is at or past
+/// the end) and `Overlong` if no such byte is within `MAX_VARINT_BYTES`.
+#[allow(dead_code)]
+pub fn skip_varint(bytes: &[u8], offset: usize) -> Result<usize, DecodeError> {
+    let rest = bytes.get(offset..).unwrap_or(&[]);
+    match rest
+        .iter()
+        .take(MAX_VARINT_BYTES)
+        .position(|&b| b & CONTINUATION_BIT == 0)
+    {
+        Some(i) => Ok(offset + i + 1),
+        None if rest.len() > MAX_VARINT_BYTES => Err(DecodeError::Overlong),
+        None => Err(DecodeError::Truncated),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,10 +373,231 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn decode_many_values() {
         let values = decode_many(&[0x00, 0x7F, 0x80, 0x01]);
         assert_eq!(values.len(), 3);
         assert_eq!(values[0], 0);
         assert_eq!(values[1], 127);
     }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn svarint_roundtrip() {
+        use crate::encoder::encode_svarint;
+
+        for value in [0, -1, 1, -64, 64, i64::MIN, i64::MAX] {
+            let encoded = encode_svarint(value);
+            assert_eq!(decode_svarint(&encoded), (value, encoded.len()), "value {}", value);
+        }
+
+        // Zigzag keeps small magnitudes in one byte.
+        assert_eq!(encode_svarint(0), vec![0x00]);
+        assert_eq!(encode_svarint(-1), vec![0x01]);
+        assert_eq!(encode_svarint(1), vec![0x02]);
+        assert_eq!(encode_svarint(i64::MIN).len(), MAX_VARINT_BYTES);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_varint_from_stream() {
+        use crate::encoder::encode_many;
+
+        let values = [0, 1, 127, 128, 300, 16384, u64::MAX];
+        let bytes = encode_many(&values);
+        let mut reader = &bytes[..];
+
+        for &v in &values {
+            assert_eq!(read_varint(&mut reader).unwrap(), v);
+        }
+        let eof = read_varint(&mut reader).unwrap_err();
+        assert_eq!(eof.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_varint_rejects_truncated_and_overlong() {
+        let truncated = read_varint(&mut &[0xAC][..]).unwrap_err();
+        assert_eq!(truncated.kind(), io::ErrorKind::InvalidData);
+
+        let overlong = [0x80u8; MAX_VARINT_BYTES + 1];
+        let mut reader = &overlong[..];
+        let err = read_varint(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(reader.len(), 1, "must stop after MAX_VARINT_BYTES");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn incremental_decoder_across_chunks() {
+        use crate::encoder::encode_many;
+
+        let values = [5, 300, u64::MAX, 0, 16384];
+        let bytes = encode_many(&values);
+
+        // Feed in awkward chunk sizes so varints straddle boundaries.
+        let mut decoder = VarintDecoder::new();
+        let mut out = Vec::new();
+        for chunk in bytes.chunks(3) {
+            for &b in chunk {
+                out.extend(decoder.push(b));
+            }
+        }
+        assert_eq!(out, values);
+        assert!(!decoder.in_progress());
+
+        assert_eq!(decoder.push(0xAC), None);
+        assert!(decoder.in_progress());
+        assert_eq!(decoder.bytes_read(), 1);
+        assert_eq!(decoder.push(0x02), Some(300));
+    }
+
+    #[test]
+    fn incremental_decoder_overlong() {
+        let mut decoder = VarintDecoder::new();
+        for _ in 0..MAX_VARINT_BYTES {
+            assert_eq!(decoder.push(0x80), None);
+        }
+        assert!(!decoder.is_overlong());
+        assert_eq!(decoder.push(0x01), None);
+        assert!(decoder.is_overlong());
+        assert_eq!(decoder.push(0x01), None);
+
+        decoder.reset();
+        assert!(!decoder.is_overlong());
+        assert_eq!(decoder.push(0x01), Some(1));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn try_decode_reports_errors() {
+        use crate::encoder::encode_varint;
+
+        for v in [0, 300, u64::MAX] {
+            let bytes = encode_varint(v);
+            assert_eq!(try_decode_varint(&bytes), Ok(DecodeResult::new(v, bytes.len())));
+        }
+
+        assert_eq!(try_decode_varint(&[]), Err(DecodeError::Truncated));
+        assert_eq!(try_decode_varint(&[0xAC]), Err(DecodeError::Truncated));
+        assert_eq!(try_decode_varint(&[0x80; 11]), Err(DecodeError::Overlong));
+
+        // Tenth byte may only contribute bit 63.
+        let mut too_big = vec![0xFF; 9];
+        too_big.push(0x02);
+        assert_eq!(try_decode_varint(&too_big), Err(DecodeError::Overflow));
+
+        // The lenient decoder keeps its old best-effort answers.
+        assert_eq!(decode_varint(&[0xAC]), DecodeResult::new(0x2C, 1));
+        assert_eq!(decode_varint(&[0x80; 11]).bytes_read, MAX_VARINT_BYTES);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn canonical_decode_rejects_padding() {
+        use crate::encoder::encode_varint;
+
+        assert_eq!(decode_varint_canonical(&[0x00]), Ok(DecodeResult::new(0, 1)));
+        assert_eq!(decode_varint_canonical(&[0x80, 0x00]), Err(DecodeError::NonCanonical));
+        assert_eq!(decode_varint_canonical(&[0xAC, 0x82, 0x00]), Err(DecodeError::NonCanonical));
+        assert_eq!(decode_varint_canonical(&[0xAC]), Err(DecodeError::Truncated));
+
+        for v in [1, 127, 128, 300, u64::MAX] {
+            let bytes = encode_varint(v);
+            assert_eq!(decode_varint_canonical(&bytes), Ok(DecodeResult::new(v, bytes.len())));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn u32_and_u128_roundtrip() {
+        use crate::encoder::{encode_varint, encode_varint_u128, encode_varint_u32};
+        use crate::types::{MAX_VARINT_BYTES_128, MAX_VARINT_BYTES_32};
+
+        for v in [0, 1, 300, u32::MAX] {
+            let bytes = encode_varint_u32(v);
+            assert_eq!(bytes, encode_varint(v as u64));
+            assert_eq!(decode_varint_u32(&bytes), Ok((v, bytes.len())));
+        }
+        assert_eq!(encode_varint_u32(u32::MAX).len(), MAX_VARINT_BYTES_32);
+
+        for v in [0, 300, u64::MAX as u128 + 1, u128::MAX] {
+            let bytes = encode_varint_u128(v);
+            assert_eq!(decode_varint_u128(&bytes), Ok((v, bytes.len())));
+        }
+        assert_eq!(encode_varint_u128(u128::MAX).len(), MAX_VARINT_BYTES_128);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn u32_decode_range_checks() {
+        use crate::encoder::encode_varint;
+
+        let too_big = encode_varint(u32::MAX as u64 + 1);
+        assert_eq!(decode_varint_u32(&too_big), Err(DecodeError::Overflow));
+        assert_eq!(decode_varint_u32(&[0x80; 6]), Err(DecodeError::Overlong));
+        assert_eq!(decode_varint_u128(&[0x80; 20]), Err(DecodeError::Overlong));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn varint_iter_is_lazy_and_reports_truncation() {
+        use crate::encoder::encode_many;
+
+        let values = [0, 127, 128, 300, u64::MAX];
+        let bytes = encode_many(&values);
+        let decoded: Result<Vec<u64>, DecodeError> = VarintIter::new(&bytes).collect();
+        assert_eq!(decoded, Ok(values.to_vec()));
+
+        let mut iter = VarintIter::new(&bytes);
+        assert_eq!(iter.next(), Some(Ok(0)));
+        assert_eq!(iter.offset(), 1);
+
+        let mut truncated = encode_many(&[1, 2]);
+        truncated.push(0xAC);
+        let items: Vec<_> = VarintIter::new(&truncated).collect();
+        assert_eq!(items, vec![Ok(1), Ok(2), Err(DecodeError::Truncated)]);
+
+        assert_eq!(VarintIter::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn decode_with_max_bound() {
+        use crate::encoder::encode_varint_array;
+
+        const MIB: u64 = 1 << 20;
+        for (value, ok) in [(MIB - 1, true), (MIB, true), (MIB + 1, false)] {
+            let (buf, len) = encode_varint_array(value);
+            let expected = if ok {
+                Ok(DecodeResult::new(value, len))
+            } else {
+                Err(DecodeError::ExceedsMax)
+            };
+            assert_eq!(decode_varint_max(&buf[..len], MIB), expected, "value {}", value);
+        }
+
+        // Rejected from the first byte, before truncation or overlong
+        // length could be noticed.
+        assert_eq!(decode_varint_max(&[0xFF, 0x80], 100), Err(DecodeError::ExceedsMax));
+        assert_eq!(decode_varint_max(&[0xFF; 20], 100), Err(DecodeError::ExceedsMax));
+        assert_eq!(decode_varint_max(&[0x80, 0x80], 100), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn skip_varint_advances_past_fields() {
+        // 300, 5, u64::MAX
+        let mut bytes = vec![0xAC, 0x02, 0x05];
+        bytes.extend([0xFF; 9]);
+        bytes.push(0x01);
+
+        assert_eq!(skip_varint(&bytes, 0), Ok(2));
+        assert_eq!(skip_varint(&bytes, 2), Ok(3));
+        assert_eq!(skip_varint(&bytes, 3), Ok(bytes.len()));
+        assert_eq!(decode_varint_at(&bytes, 2), (5, 3));
+
+        assert_eq!(skip_varint(&bytes, bytes.len()), Err(DecodeError::Truncated));
+        assert_eq!(skip_varint(&[0xAC], 0), Err(DecodeError::Truncated));
+        assert_eq!(skip_varint(&[0x80; 10], 0), Err(DecodeError::Truncated));
+        assert_eq!(skip_varint(&[0x80; 11], 0), Err(DecodeError::Overlong));
+    }
 }
@@ -1,4 +1,9 @@
-use crate::types::{CONTINUATION_BIT, DATA_BITS, DATA_MASK, MAX_VARINT_BYTES};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+#[cfg(feature = "alloc")]
+use crate::types::zigzag_encode;
+use crate::types::{encoded_len, VarintInt, CONTINUATION_BIT, MAX_VARINT_BYTES};
 
 /// Encode `value` as a variable-length integer (varint).
 ///
@@ -6,72 +11,149 @@ use crate::types::{CONTINUATION_BIT, DATA_BITS, DATA_MASK, MAX_VARINT_BYTES};
 /// The MSB (0x80) is the continuation flag: it is set on every byte
 /// except the final one.
 ///
+/// Bytes are emitted little-endian (least significant group first), the
+/// order `decode_varint` expects.
+///
 /// Examples:
 ///   0       -> [0x00]                  (1 byte)
 ///   127     -> [0x7F]                  (1 byte)
-///   128     -> [0x01, 0x80]            (2 bytes)
-///   300     -> [0x02, 0xAC]            (2 bytes)
-///   16383   -> [0x7F, 0xFF]            (2 bytes)
-///   16384   -> [0x01, 0x80, 0x80]      (3 bytes)
-///   65535   -> [0x03, 0xFF, 0xFF]      (3 bytes)
-pub fn encode_varint(mut value: u64) -> Vec<u8> {
-    let mut buf = Vec::with_capacity(MAX_VARINT_BYTES);
-
-    loop {
-        let mut byte = (value & DATA_MASK as u64) as u8;
-        value >>= DATA_BITS;
-
-        if value != 0 {
-            byte |= CONTINUATION_BIT;
+///   128     -> [0x80, 0x01]            (2 bytes)
+///   300     -> [0xAC, 0x02]            (2 bytes)
+///   16383   -> [0xFF, 0x7F]            (2 bytes)
+///   16384   -> [0x80, 0x80, 0x01]      (3 bytes)
+///   65535   -> [0xFF, 0xFF, 0x03]      (3 bytes)
+#[cfg(feature = "alloc")]
+pub fn encode_varint(value: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(encoded_len(value));
+    buf.extend(varint_bytes(value));
+    buf
+}
+
+/// Encode a `u32` as a varint (at most `MAX_VARINT_BYTES_32` bytes).
+#[allow(dead_code)]
+#[cfg(feature = "alloc")]
+pub fn encode_varint_u32(value: u32) -> Vec<u8> {
+    varint_bytes(value).collect()
+}
+
+/// Encode a `u128` as a varint (at most `MAX_VARINT_BYTES_128` bytes).
+#[allow(dead_code)]
+#[cfg(feature = "alloc")]
+pub fn encode_varint_u128(value: u128) -> Vec<u8> {
+    varint_bytes(value).collect()
+}
+
+/// The bytes of `value`'s varint encoding, in output order.
+///
+/// This is the one place the 7-bits-plus-continuation split lives; every
+/// encoder in this module is built on it.
+fn varint_bytes<T: VarintInt>(mut value: T) -> impl Iterator<Item = u8> {
+    let mut done = false;
+    core::iter::from_fn(move || {
+        if done {
+            return None;
         }
 
-        buf.push(byte);
+        let mut byte = value.low_group();
+        value = value.shift_group();
 
-        if value == 0 {
-            break;
+        if value != T::ZERO {
+            byte |= CONTINUATION_BIT;
+        } else {
+            done = true;
         }
-    }
 
-    // Store in network byte order (most significant byte first)
-    buf.reverse();
-    buf
+        Some(byte)
+    })
+}
+
+/// Encode a signed `value` as a zigzag varint (protobuf `sint64`), so
+/// small negative numbers take as few bytes as small positive ones.
+#[allow(dead_code)]
+#[cfg(feature = "alloc")]
+pub fn encode_svarint(value: i64) -> Vec<u8> {
+    encode_varint(zigzag_encode(value))
+}
+
+/// Encode `value` into a stack array, returning it and the number of
+/// bytes used.  Needs no allocator; the bytes are `array[..len]`.
+pub fn encode_varint_array(value: u64) -> ([u8; MAX_VARINT_BYTES], usize) {
+    let mut buf = [0u8; MAX_VARINT_BYTES];
+    let len = encode_varint_into(value, &mut buf, 0);
+    (buf, len)
 }
 
 /// Encode `value` into a pre-allocated buffer starting at `offset`.
 ///
 /// Returns the number of bytes written.  Panics if the buffer is too
 /// small (fewer than `MAX_VARINT_BYTES` remaining).
-pub fn encode_varint_into(mut value: u64, buf: &mut [u8], offset: usize) -> usize {
+#[allow(dead_code)]
+pub fn encode_varint_into(value: u64, buf: &mut [u8], offset: usize) -> usize {
     let mut i = offset;
 
-    loop {
+    for byte in varint_bytes(value) {
         assert!(
             i < buf.len(),
             "buffer overflow: need more than {} bytes to encode varint",
             buf.len() - offset
         );
 
-        let mut byte = (value & DATA_MASK as u64) as u8;
-        value >>= DATA_BITS;
-
-        if value != 0 {
-            byte |= CONTINUATION_BIT;
-        }
-
         buf[i] = byte;
         i += 1;
+    }
+
+    i - offset
+}
 
-        if value == 0 {
-            break;
+/// Encode `values` back to back into `buf` starting at `offset`.
+///
+/// Returns the total number of bytes written, or `Err(i)` if `values[i]`
+/// does not fit in the space left.  Each value's size is checked before
+/// any of its bytes are written, so on error `values[..i]` remain encoded
+/// in `buf[offset..]` and nothing after them is touched.
+#[allow(dead_code)]
+pub fn encode_many_into(values: &[u64], buf: &mut [u8], offset: usize) -> Result<usize, usize> {
+    let mut pos = offset;
+    for (i, &v) in values.iter().enumerate() {
+        if pos + encoded_len(v) > buf.len() {
+            return Err(i);
         }
+        pos += encode_varint_into(v, buf, pos);
     }
+    Ok(pos - offset)
+}
 
-    // Reverse to network byte order
-    buf[offset..i].reverse();
-    i - offset
+/// Write `value` as a varint to `w` without an intermediate `Vec`.
+///
+/// Bytes are written one at a time; returns how many were written.  I/O
+/// errors are returned as-is, possibly after part of the varint has
+/// been written.
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+pub fn write_varint<W: Write>(w: &mut W, value: u64) -> io::Result<usize> {
+    let mut written = 0;
+    for byte in varint_bytes(value) {
+        w.write_all(&[byte])?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Write each of `values` as a varint to `w`, returning the total number
+/// of bytes written.  Stops at the first I/O error.
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+pub fn write_many<W: Write>(w: &mut W, values: &[u64]) -> io::Result<usize> {
+    let mut written = 0;
+    for &v in values {
+        written += write_varint(w, v)?;
+    }
+    Ok(written)
 }
 
 /// Encode a sequence of values into a single byte stream.
+#[allow(dead_code)]
+#[cfg(feature = "alloc")]
 pub fn encode_many(values: &[u64]) -> Vec<u8> {
     let mut buf = Vec::new();
     for &v in values {
@@ -85,37 +167,55 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_zero() {
         assert_eq!(encode_varint(0), vec![0x00]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_single_byte_max() {
         assert_eq!(encode_varint(127), vec![0x7F]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_two_byte_min() {
-        // 128: LE would be [0x80, 0x01], reversed to [0x01, 0x80]
-        assert_eq!(encode_varint(128), vec![0x01, 0x80]);
+        // 128: low group 0x00 with continuation, then 0x01
+        assert_eq!(encode_varint(128), vec![0x80, 0x01]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_300() {
-        // 300: LE would be [0xAC, 0x02], reversed to [0x02, 0xAC]
-        assert_eq!(encode_varint(300), vec![0x02, 0xAC]);
+        // 300 = 0b10_0101100: low group 0x2C | 0x80, then 0x02
+        assert_eq!(encode_varint(300), vec![0xAC, 0x02]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_two_byte_max() {
-        // 16383: LE would be [0xFF, 0x7F], reversed to [0x7F, 0xFF]
-        assert_eq!(encode_varint(16383), vec![0x7F, 0xFF]);
+        // 16383: two full 7-bit groups
+        assert_eq!(encode_varint(16383), vec![0xFF, 0x7F]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_three_bytes() {
-        // 65535: LE would be [0xFF, 0xFF, 0x03], reversed to [0x03, 0xFF, 0xFF]
-        assert_eq!(encode_varint(65535), vec![0x03, 0xFF, 0xFF]);
+        // 65535: two full 7-bit groups, then the remaining 0x03
+        assert_eq!(encode_varint(65535), vec![0xFF, 0xFF, 0x03]);
+    }
+
+    #[test]
+    fn encode_into_array() {
+        assert_eq!(encode_varint_array(0), ([0; MAX_VARINT_BYTES], 1));
+
+        let (buf, len) = encode_varint_array(300);
+        assert_eq!(&buf[..len], &[0xAC, 0x02]);
+
+        let (buf, len) = encode_varint_array(u64::MAX);
+        assert_eq!(len, MAX_VARINT_BYTES);
+        assert_eq!(buf[MAX_VARINT_BYTES - 1], 0x01);
     }
 
     #[test]
@@ -123,14 +223,97 @@ mod tests {
         let mut buf = [0u8; 16];
         let n = encode_varint_into(300, &mut buf, 0);
         assert_eq!(n, 2);
-        assert_eq!(&buf[..2], &[0x02, 0xAC]);
+        assert_eq!(&buf[..2], &[0xAC, 0x02]);
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn encode_many_values() {
         let values = vec![0, 127, 128, 300];
         let encoded = encode_many(&values);
-        // 0 → [0x00], 127 → [0x7F], 128 → [0x01, 0x80], 300 → [0x02, 0xAC]
-        assert_eq!(encoded, vec![0x00, 0x7F, 0x01, 0x80, 0x02, 0xAC]);
+        // 0 → [0x00], 127 → [0x7F], 128 → [0x80, 0x01], 300 → [0xAC, 0x02]
+        assert_eq!(encoded, vec![0x00, 0x7F, 0x80, 0x01, 0xAC, 0x02]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn write_matches_encode() {
+        let values = [0, 127, 128, 300, 65535, u64::MAX];
+
+        let mut out = Vec::new();
+        for &v in &values {
+            let n = write_varint(&mut out, v).unwrap();
+            assert_eq!(n, encode_varint(v).len());
+        }
+        assert_eq!(out, encode_many(&values));
+
+        let mut out = Vec::new();
+        let total = write_many(&mut out, &values).unwrap();
+        assert_eq!(total, out.len());
+        assert_eq!(out, encode_many(&values));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn write_propagates_io_errors() {
+        // Accepts `room` bytes, then fails.
+        struct Limited {
+            room: usize,
+        }
+        impl Write for Limited {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.room == 0 {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+                }
+                let n = buf.len().min(self.room);
+                self.room -= n;
+                Ok(n)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = write_varint(&mut Limited { room: 1 }, 300).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        assert!(write_many(&mut Limited { room: 3 }, &[1, 300]).is_ok());
+        assert!(write_many(&mut Limited { room: 3 }, &[1, 300, 2]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encode_many_into_buffer() {
+        let values = [1, 300, 65535];
+        let mut buf = [0xEEu8; 8];
+
+        assert_eq!(encode_many_into(&values, &mut buf, 1), Ok(6));
+        assert_eq!(&buf[1..7], &encode_many(&values)[..]);
+        assert_eq!((buf[0], buf[7]), (0xEE, 0xEE));
+
+        // 65535 needs 3 bytes but only 2 remain after 1 and 300.
+        let mut buf = [0xEEu8; 6];
+        assert_eq!(encode_many_into(&values, &mut buf, 1), Err(2));
+        assert_eq!(&buf[1..4], &[0x01, 0xAC, 0x02]);
+        assert_eq!(&buf[4..], &[0xEE, 0xEE]);
+
+        assert_eq!(encode_many_into(&[], &mut buf, 6), Ok(0));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encoded_len_matches_encoding() {
+        const ZERO_LEN: usize = encoded_len(0);
+        let _sized: [u8; encoded_len(u64::MAX)] = [0; 10];
+        assert_eq!(ZERO_LEN, 1);
+
+        let mut v: u64 = 1;
+        while v != 0 {
+            for value in [v - 1, v, v | (v - 1)] {
+                let encoded = encode_varint(value);
+                assert_eq!(encoded_len(value), encoded.len(), "value {}", value);
+            }
+            v <<= 1;
+        }
+        assert_eq!(encoded_len(u64::MAX), encode_varint(u64::MAX).len());
     }
 }
@@ -0,0 +1,87 @@
+use std::io::{self, Read, Write};
+
+use crate::decoder::read_varint;
+use crate::encoder::write_varint;
+
+/// Write `payload` prefixed with its length as a varint.
+///
+/// Returns the total number of bytes written (prefix plus payload).
+#[allow(dead_code)]
+pub fn write_message<W: Write>(w: &mut W, payload: &[u8]) -> io::Result<usize> {
+    let prefix = write_varint(w, payload.len() as u64)?;
+    w.write_all(payload)?;
+    Ok(prefix + payload.len())
+}
+
+/// Read one length-prefixed message written by `write_message`.
+///
+/// With `max_len` set, a declared length above it fails with
+/// `InvalidData` before anything is allocated.  Without it, the payload
+/// buffer still only grows as bytes actually arrive, so a corrupt prefix
+/// cannot force a huge allocation up front.  A stream that ends inside
+/// the payload fails with `UnexpectedEof`; errors from the length prefix
+/// are those of `read_varint`.
+#[allow(dead_code)]
+pub fn read_message<R: Read>(r: &mut R, max_len: Option<usize>) -> io::Result<Vec<u8>> {
+    let declared = read_varint(r)?;
+
+    let len = usize::try_from(declared)
+        .ok()
+        .filter(|&len| max_len.is_none_or(|max| len <= max))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message length {} exceeds limit {:?}", declared, max_len),
+            )
+        })?;
+
+    let mut payload = Vec::new();
+    r.take(declared).read_to_end(&mut payload)?;
+    if payload.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("message truncated: expected {} bytes, got {}", len, payload.len()),
+        ));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_roundtrip() {
+        let messages: [&[u8]; 3] = [b"hello", b"", &[0xAB; 300]];
+
+        let mut stream = Vec::new();
+        for m in messages {
+            let n = write_message(&mut stream, m).unwrap();
+            assert_eq!(n, crate::encoder::encode_varint(m.len() as u64).len() + m.len());
+        }
+
+        let mut reader = &stream[..];
+        for m in messages {
+            assert_eq!(read_message(&mut reader, Some(300)).unwrap(), m);
+        }
+        let end = read_message(&mut reader, None).unwrap_err();
+        assert_eq!(end.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn message_length_limit_and_truncation() {
+        let mut stream = Vec::new();
+        write_message(&mut stream, &[1, 2, 3, 4]).unwrap();
+
+        let err = read_message(&mut &stream[..], Some(3)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read_message(&mut &stream[..4], None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // A huge declared length with no limit fails on EOF, not on allocation.
+        let huge = crate::encoder::encode_varint(u64::MAX >> 1);
+        let err = read_message(&mut &huge[..], None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
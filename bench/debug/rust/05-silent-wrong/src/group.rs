@@ -0,0 +1,106 @@
+use crate::types::DecodeError;
+
+/// Group varint: four integers share one control byte.
+///
+/// Bits `2i..2i+1` of the control byte hold `len - 1` for value `i`
+/// (value 0 in the low bits), so each value takes 1–4 bytes.  The data
+/// bytes follow, little-endian, in value order.  A group is therefore
+/// 5–17 bytes and decodes without a per-byte continuation branch.
+#[allow(dead_code)]
+pub const MAX_GROUP_BYTES: usize = 17;
+
+/// Bytes needed for `value` in a group (1–4).
+#[cfg(feature = "alloc")]
+fn group_len(value: u32) -> usize {
+    (4 - value.leading_zeros() as usize / 8).max(1)
+}
+
+/// Encode four values as one group.
+///
+/// Panics if any value exceeds `u32::MAX`, since a 2-bit length field
+/// cannot describe more than 4 bytes.
+#[cfg(feature = "alloc")]
+#[allow(dead_code)]
+pub fn encode_group(values: &[u64; 4]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(MAX_GROUP_BYTES);
+    buf.push(0);
+
+    for (i, &v) in values.iter().enumerate() {
+        let v = u32::try_from(v).expect("group varint values must fit in 32 bits");
+        let len = group_len(v);
+        buf[0] |= ((len - 1) as u8) << (2 * i);
+        buf.extend_from_slice(&v.to_le_bytes()[..len]);
+    }
+
+    buf
+}
+
+/// Decode one group from the front of `bytes`, returning the four values
+/// and the number of bytes consumed.  Fails with `Truncated` if `bytes`
+/// is shorter than the control byte says.
+#[allow(dead_code)]
+pub fn decode_group(bytes: &[u8]) -> Result<([u64; 4], usize), DecodeError> {
+    let (&control, data) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+
+    let mut values = [0u64; 4];
+    let mut pos = 0;
+    for (i, value) in values.iter_mut().enumerate() {
+        let len = ((control >> (2 * i)) & 0b11) as usize + 1;
+        let chunk = data.get(pos..pos + len).ok_or(DecodeError::Truncated)?;
+
+        let mut le = [0u8; 4];
+        le[..len].copy_from_slice(chunk);
+        *value = u32::from_le_bytes(le) as u64;
+        pos += len;
+    }
+
+    Ok((values, 1 + pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_known_group() {
+        // Lengths 1, 2, 1, 4 -> control 0b11_00_01_00.
+        let bytes = [0b1100_0100, 0x05, 0x2C, 0x01, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xAA];
+        assert_eq!(decode_group(&bytes), Ok(([5, 300, 0, u32::MAX as u64], 9)));
+        assert_eq!(decode_group(&bytes[..8]), Err(DecodeError::Truncated));
+        assert_eq!(decode_group(&[]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn group_roundtrip_mixed_lengths() {
+        let groups: [[u64; 4]; 4] = [
+            [0, 0, 0, 0],
+            [1, 0xFFFF_FFFF, 2, 0x0100_0000],
+            [0xFF, 0x100, 0xFFFF, 0x1_0000],
+            [u32::MAX as u64; 4],
+        ];
+
+        let mut stream = Vec::new();
+        for g in &groups {
+            let encoded = encode_group(g);
+            assert!(encoded.len() >= 5 && encoded.len() <= MAX_GROUP_BYTES);
+            stream.extend(encoded);
+        }
+        assert_eq!(encode_group(&groups[1]).len(), 1 + 1 + 4 + 1 + 4);
+
+        let mut offset = 0;
+        for g in &groups {
+            let (values, used) = decode_group(&stream[offset..]).unwrap();
+            assert_eq!(&values, g);
+            offset += used;
+        }
+        assert_eq!(offset, stream.len());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[should_panic(expected = "32 bits")]
+    fn group_rejects_wide_values() {
+        encode_group(&[0, 0, u32::MAX as u64 + 1, 0]);
+    }
+}
@@ -1,9 +1,12 @@
 mod types;
 mod encoder;
 mod decoder;
+#[cfg(feature = "std")]
+mod framing;
+mod group;
 
 use types::{expected_byte_count, hex_dump};
-use encoder::encode_varint;
+use encoder::encode_varint_array;
 use decoder::decode_varint;
 
 /// Test values spanning the range from 1-byte to multi-byte varints.
@@ -29,8 +32,9 @@ fn main() {
     println!();
 
     for &original in &TEST_VALUES {
-        let encoded = encode_varint(original);
-        let decoded = decode_varint(&encoded);
+        let (buf, len) = encode_varint_array(original);
+        let encoded = &buf[..len];
+        let decoded = decode_varint(encoded);
 
         let expected_bytes = expected_byte_count(original);
         let ok = decoded.value == original;
@@ -39,7 +43,7 @@ fn main() {
             failures.push(RoundtripFailure {
                 original,
                 decoded: decoded.value,
-                encoded_hex: hex_dump(&encoded),
+                encoded_hex: hex_dump(encoded),
                 expected_bytes,
                 actual_bytes: decoded.bytes_read,
             });
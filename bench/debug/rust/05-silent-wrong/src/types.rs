@@ -10,6 +10,53 @@ pub const CONTINUATION_BIT: u8 = 0x80;
 /// Maximum number of bytes a 64-bit varint can occupy (ceil(64/7) = 10).
 pub const MAX_VARINT_BYTES: usize = 10;
 
+/// Maximum number of bytes a 32-bit varint can occupy (ceil(32/7) = 5).
+pub const MAX_VARINT_BYTES_32: usize = 5;
+
+/// Maximum number of bytes a 128-bit varint can occupy (ceil(128/7) = 19).
+pub const MAX_VARINT_BYTES_128: usize = 19;
+
+/// Unsigned integer types the varint codec works over.
+pub trait VarintInt: Copy + PartialEq {
+    /// Longest valid encoding of this type.
+    const MAX_BYTES: usize;
+    const ZERO: Self;
+
+    /// The low `DATA_BITS` bits.
+    fn low_group(self) -> u8;
+
+    /// `self >> DATA_BITS`.
+    fn shift_group(self) -> Self;
+
+    /// `self | (group << shift)`, and whether every bit of `group` fit.
+    /// `shift` must be below the type's bit width.
+    fn with_group(self, group: u8, shift: u32) -> (Self, bool);
+}
+
+macro_rules! impl_varint_int {
+    ($($t:ty => $max:expr),*) => {$(
+        impl VarintInt for $t {
+            const MAX_BYTES: usize = $max;
+            const ZERO: Self = 0;
+
+            fn low_group(self) -> u8 {
+                (self & DATA_MASK as $t) as u8
+            }
+
+            fn shift_group(self) -> Self {
+                self >> DATA_BITS
+            }
+
+            fn with_group(self, group: u8, shift: u32) -> (Self, bool) {
+                let data = group as $t;
+                (self | data << shift, data.leading_zeros() >= shift)
+            }
+        }
+    )*};
+}
+
+impl_varint_int!(u32 => MAX_VARINT_BYTES_32, u64 => MAX_VARINT_BYTES, u128 => MAX_VARINT_BYTES_128);
+
 /// The result of decoding a varint: the value and how many bytes were consumed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DecodeResult {
@@ -23,8 +70,8 @@ impl DecodeResult {
     }
 }
 
-impl std::fmt::Display for DecodeResult {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for DecodeResult {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "DecodeResult {{ value: {}, bytes_read: {} }}",
@@ -33,6 +80,50 @@ impl std::fmt::Display for DecodeResult {
     }
 }
 
+/// Why a byte sequence is not a valid varint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum DecodeError {
+    /// The input ended while the continuation bit was still set.
+    Truncated,
+    /// More bytes than the target type allows (`MAX_VARINT_BYTES` for
+    /// `u64`) without a terminating byte.
+    Overlong,
+    /// The encoded value does not fit in the target type.
+    Overflow,
+    /// A multi-byte varint whose last byte is `0x00`: the same value has a
+    /// shorter encoding.
+    NonCanonical,
+    /// The value is larger than the caller's limit.
+    ExceedsMax,
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "truncated varint"),
+            DecodeError::Overlong => write!(f, "varint too long for its type"),
+            DecodeError::Overflow => write!(f, "varint value overflows its type"),
+            DecodeError::NonCanonical => write!(f, "non-minimal varint encoding"),
+            DecodeError::ExceedsMax => write!(f, "varint value exceeds limit"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Zigzag-map a signed value onto an unsigned one so small magnitudes stay
+/// small: 0 -> 0, -1 -> 1, 1 -> 2, -2 -> 3, ...  (protobuf `sint64`).
+#[allow(dead_code)]
+pub fn zigzag_encode(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+/// Inverse of [`zigzag_encode`].
+pub fn zigzag_decode(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
 /// Format a byte slice as a hex string for debugging.
 ///
 /// Example: `[0xAC, 0x02]` -> `"ac 02"`
@@ -44,11 +135,67 @@ pub fn hex_dump(bytes: &[u8]) -> String {
         .join(" ")
 }
 
+/// Parse hex written by [`hex_dump`] back into bytes.
+///
+/// Tokens are separated by any whitespace and each must be exactly two hex
+/// digits (`"ac 02"` gives `[0xAC, 0x02]`).  Non-hex characters and tokens
+/// of any other length, such as `"ac02"`, are errors.
+#[cfg(feature = "alloc")]
+#[allow(dead_code)]
+pub fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for token in s.split_whitespace() {
+        if let Some(bad) = token.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex character {:?} in {:?}", bad, token));
+        }
+        if token.len() != 2 {
+            return Err(format!("hex token {:?} is not two digits", token));
+        }
+        bytes.push(u8::from_str_radix(token, 16).expect("validated hex pair"));
+    }
+    Ok(bytes)
+}
+
 /// Calculate the expected number of bytes needed to encode `value`.
 pub fn expected_byte_count(value: u64) -> usize {
+    encoded_len(value)
+}
+
+/// Number of bytes `encode_varint(value)` produces: `ceil(bits / 7)`, or
+/// 1 for zero.  Usable in const contexts, e.g. to size an array.
+pub const fn encoded_len(value: u64) -> usize {
     if value == 0 {
         return 1;
     }
     let bits = 64 - value.leading_zeros() as usize;
-    (bits + DATA_BITS as usize - 1) / DATA_BITS as usize
+    bits.div_ceil(DATA_BITS as usize)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_inverts_hex_dump() {
+        assert_eq!(from_hex("ac 02"), Ok(vec![0xAC, 0x02]));
+        assert_eq!(from_hex("  AC\t02\n ff "), Ok(vec![0xAC, 0x02, 0xFF]));
+        assert_eq!(from_hex(""), Ok(vec![]));
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(from_hex(&hex_dump(&bytes)), Ok(bytes));
+
+        assert_eq!(
+            crate::decoder::decode_varint(&from_hex("ac 02").unwrap()).value,
+            300
+        );
+    }
+
+    #[test]
+    fn from_hex_rejects_bad_tokens() {
+        assert!(from_hex("ac 0").unwrap_err().contains("not two digits"));
+        assert!(from_hex("ac02").unwrap_err().contains("not two digits"));
+        assert!(from_hex("zz").unwrap_err().contains("'z'"));
+        assert!(from_hex("+a").is_err());
+        assert!(from_hex("0x10").is_err());
+    }
 }